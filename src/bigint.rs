@@ -0,0 +1,212 @@
+//! An arbitrary-precision unsigned integer.
+
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul};
+
+/// An unsigned integer that never overflows.
+///
+/// The value is stored as base-2<sup>32</sup> digits, least significant
+/// first, with no zero digits at the most significant end. Zero has no
+/// digits at all.
+///
+/// # Examples
+///
+/// ```
+/// use advent_of_code_2021::BigUint;
+/// let big = BigUint::from(u64::MAX) * BigUint::from(u64::MAX);
+/// assert_eq!(big.to_string(), "340282366920938463426481119284349108225");
+/// assert_eq!(big.to_u64(), None);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BigUint {
+    digits: Vec<u32>,
+}
+
+impl BigUint {
+    /// Returns zero.
+    pub fn zero() -> Self {
+        BigUint { digits: Vec::new() }
+    }
+
+    /// Returns true if the value is zero.
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    /// Returns the value as a u64, or None if it doesn't fit.
+    pub fn to_u64(&self) -> Option<u64> {
+        match self.digits.len() {
+            0 => Some(0),
+            1 => Some(self.digits[0] as u64),
+            2 => Some(self.digits[0] as u64 | (self.digits[1] as u64) << 32),
+            _ => None,
+        }
+    }
+
+    /// Returns the remainder of dividing the value by `divisor`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `divisor` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use advent_of_code_2021::BigUint;
+    /// let big = BigUint::from(u64::MAX) * BigUint::from(10u64);
+    /// assert_eq!(big.rem_u64(1_000_000_007), 823440035);
+    /// ```
+    pub fn rem_u64(&self, divisor: u64) -> u64 {
+        assert!(divisor > 0, "Division by zero");
+        let mut rem: u128 = 0;
+        for &digit in self.digits.iter().rev() {
+            rem = ((rem << 32) | digit as u128) % divisor as u128;
+        }
+        rem as u64
+    }
+
+    // Removes leading zero digits, so each value has one representation.
+    fn normalize(&mut self) {
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+    }
+
+    // Divides in place by a small divisor, returning the remainder.
+    fn div_rem_small(&mut self, divisor: u32) -> u32 {
+        let mut rem: u64 = 0;
+        for digit in self.digits.iter_mut().rev() {
+            let cur = (rem << 32) | *digit as u64;
+            *digit = (cur / divisor as u64) as u32;
+            rem = cur % divisor as u64;
+        }
+        self.normalize();
+        rem as u32
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(value: u64) -> Self {
+        let mut big = BigUint { digits: vec![value as u32, (value >> 32) as u32] };
+        big.normalize();
+        big
+    }
+}
+
+impl From<u32> for BigUint {
+    fn from(value: u32) -> Self {
+        BigUint::from(value as u64)
+    }
+}
+
+impl From<usize> for BigUint {
+    fn from(value: usize) -> Self {
+        BigUint::from(value as u64)
+    }
+}
+
+impl AddAssign<&BigUint> for BigUint {
+    fn add_assign(&mut self, other: &BigUint) {
+        if self.digits.len() < other.digits.len() {
+            self.digits.resize(other.digits.len(), 0);
+        }
+        let mut carry: u64 = 0;
+        for (i, digit) in self.digits.iter_mut().enumerate() {
+            let sum = *digit as u64 + other.digits.get(i).copied().unwrap_or(0) as u64 + carry;
+            *digit = sum as u32;
+            carry = sum >> 32;
+            if carry == 0 && i >= other.digits.len() {
+                break;
+            }
+        }
+        if carry > 0 {
+            self.digits.push(carry as u32);
+        }
+    }
+}
+
+impl AddAssign for BigUint {
+    fn add_assign(&mut self, other: BigUint) {
+        *self += &other;
+    }
+}
+
+impl Add for BigUint {
+    type Output = BigUint;
+
+    fn add(mut self, other: BigUint) -> BigUint {
+        self += &other;
+        self
+    }
+}
+
+impl<'a> Sum<&'a BigUint> for BigUint {
+    fn sum<I: Iterator<Item = &'a BigUint>>(iter: I) -> BigUint {
+        let mut total = BigUint::zero();
+        for value in iter {
+            total += value;
+        }
+        total
+    }
+}
+
+impl Sum for BigUint {
+    fn sum<I: Iterator<Item = BigUint>>(iter: I) -> BigUint {
+        let mut total = BigUint::zero();
+        for value in iter {
+            total += &value;
+        }
+        total
+    }
+}
+
+impl Mul for &BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: &BigUint) -> BigUint {
+        if self.is_zero() || other.is_zero() {
+            return BigUint::zero();
+        }
+        let mut digits = vec![0u32; self.digits.len() + other.digits.len()];
+        for (i, &a) in self.digits.iter().enumerate() {
+            let mut carry: u64 = 0;
+            for (j, &b) in other.digits.iter().enumerate() {
+                let cur = digits[i + j] as u64 + a as u64 * b as u64 + carry;
+                digits[i + j] = cur as u32;
+                carry = cur >> 32;
+            }
+            digits[i + other.digits.len()] = carry as u32;
+        }
+        let mut product = BigUint { digits };
+        product.normalize();
+        product
+    }
+}
+
+impl Mul for BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: BigUint) -> BigUint {
+        &self * &other
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.pad("0");
+        }
+        // Peel off 9 decimal digits at a time, least significant first.
+        let mut rest = self.clone();
+        let mut chunks: Vec<u32> = Vec::new();
+        while !rest.is_zero() {
+            chunks.push(rest.div_rem_small(1_000_000_000));
+        }
+        let mut s = chunks.last().unwrap().to_string();
+        for chunk in chunks.iter().rev().skip(1) {
+            s.push_str(&format!("{:09}", chunk));
+        }
+        f.pad(&s)
+    }
+}
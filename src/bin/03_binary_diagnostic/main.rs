@@ -28,7 +28,8 @@ impl DiagnosticReader {
     }
 
     // Extract each bit from the input line. Update occurences of 0/1 at each
-    // bit position.
+    // bit position. The ratings are refreshed afterwards, so they always
+    // describe every line read so far.
     fn read_line(&mut self, line: &str) {
         assert_eq!(line.chars().count(), 12, "Line does not contain 12 bits: {}:::", line);
         for (bit_pos, ch) in line.chars().enumerate() {
@@ -41,6 +42,12 @@ impl DiagnosticReader {
 
         // Update binary tree with the current line.
         self.bit_tree.insert(line.chars());
+
+        // Both computations only look at the per-column counters and one
+        // root-to-leaf path of the tree, so this stays cheap no matter how
+        // many lines have been read.
+        self.compute_power_consumption();
+        self.compute_life_support_rating();
     }
 
     fn compute_power_consumption(&mut self) {
@@ -48,19 +55,19 @@ impl DiagnosticReader {
         let mut gamma_bits = String::new();
         let mut epsilon_bits = String::new();
 
-        // Determine most common bit from each column (bit position)
+        // Determine most common bit from each column (bit position). While
+        // the data is still streaming in, a column can have equal numbers of
+        // 0s and 1s. Treat 1 as the most common bit in that case, the same
+        // tie-break the O2 generator rating uses.
         for bit_pos in 0..12 {
             if self.occurences_of_0[bit_pos] > self.occurences_of_1[bit_pos] {
                 gamma_bits.push('0');
                 epsilon_bits.push('1');
             }
-            else if self.occurences_of_1[bit_pos] > self.occurences_of_0[bit_pos] {
+            else {
                 gamma_bits.push('1');
                 epsilon_bits.push('0');
             }
-            else {
-                panic!("Bit pos {} has equal number of 0s and 1s", bit_pos);
-            }
         }
 
         // Convert binary strings into integers (e.g. "1110" = 14)
@@ -100,18 +107,17 @@ impl DiagnosticReader {
                 bits.push('1');
                 match &node.bit1 {
                     None => panic!("Subtree 1 magically disappeared"),
-                    Some(tree1) => node = &tree1
+                    Some(tree1) => node = tree1
                 };
             }
             else {
                 bits.push('0');
                 match &node.bit0 {
                     None => panic!("Subtree 0 magically disappeared"),
-                    Some(tree0) => node = &tree0
+                    Some(tree0) => node = tree0
                 };
             }
         }
-        println!("Computed O2 Generator rating = {}", bits);
         self.o2_generator_rating = i32::from_str_radix(bits.as_str(), 2).expect("O2 generator rating somehow not binary");
     }
 
@@ -161,17 +167,16 @@ impl DiagnosticReader {
             if bit == '0' {
                 match &node.bit0 {
                     None => panic!("Subtree 0 magically disappeared"),
-                    Some(tree0) => node = &tree0
+                    Some(tree0) => node = tree0
                 };
             }
             else {
                 match &node.bit1 {
                     None => panic!("Subtree 1 magically disappeared"),
-                    Some(tree1) => node = &tree1
+                    Some(tree1) => node = tree1
                 };
             }
         }
-        println!("Computed CO2 Scrubber rating = {}", bits);
         self.co2_scrubber_rating = i32::from_str_radix(bits.as_str(), 2).expect("CO2 scrubber rating somehow not binary");
     }
 
@@ -183,10 +188,22 @@ impl DiagnosticReader {
         println!("CO2 scrubber rating: {}", self.co2_scrubber_rating);
         println!("Life support rating: {}", self.life_support_rating);
     }
+
+    // Prints the current ratings on a single line. Used by the streaming
+    // output mode after each reading.
+    fn report_line(&self, line_number: usize) {
+        println!(
+            "Line {}: gamma={} epsilon={} power_consumption={} life_support_rating={}",
+            line_number, self.gamma, self.epsilon, self.power_consumption, self.life_support_rating);
+    }
 }
 
+// Pass --stream to print the ratings after every reading, instead of only
+// once the input is exhausted.
 fn main() {
+    let stream = std::env::args().skip(1).any(|arg| arg == "--stream");
     let mut diag = DiagnosticReader::new();
+    let mut line_number = 0;
     let mut done = false;
     while !done {
         let mut line = String::new();
@@ -194,11 +211,13 @@ fn main() {
             Err(e) => panic!("Error reading line: {}", e),
             Ok(0) => done = true,
             Ok(_) => {
-                diag.read_line(line.trim())
+                diag.read_line(line.trim());
+                line_number += 1;
+                if stream {
+                    diag.report_line(line_number);
+                }
             }
         };
     }
-    diag.compute_power_consumption();
-    diag.compute_life_support_rating();
     diag.report();
 }
@@ -1,10 +1,16 @@
+mod patterns;
+mod replay;
+mod simulate;
+
+use patterns::{Pattern, WinRule, WinRules};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
 type Num = i32;   // A value on the board
 type Row = usize; // A row index
 type Col = usize; // A col index
 type Score = i32; // A final score
-
-const BOARD_ROWS: Row = 5; // Number of rows for a board.
-const BOARD_COLS: Col = 5; // Number of cols for a board.
+type Turn = usize; // When a number is called (1 = the first number called)
 
 fn parse_num(int_str: &str) -> Num {
     Num::from_str_radix(int_str, 10).unwrap_or_else(|_|{
@@ -12,7 +18,13 @@ fn parse_num(int_str: &str) -> Num {
     })
 }
 
+#[derive(Clone)]
 struct Board {
+    // Dimensions of the board. These are detected from the input, so
+    // different boards may have different shapes.
+    rows: Row,
+    cols: Col,
+
     // A list of every value on the board, paired with its position. This
     // will be sorted by the value.
     layout: Vec<(Num, Row, Col)>,
@@ -20,97 +32,366 @@ struct Board {
     // A list of values that have not yet been called. This will be sorted.
     remaining: Vec<Num>,
 
-    // Number of values called for each row (e.g. called_in_row[2] = 3 means
-    // that row 2 has 3 numbers that have been called).
-    called_in_row: [usize; BOARD_ROWS],
+    // Every pattern that wins on this board (rows, columns, diagonals, ...).
+    patterns: Vec<Pattern>,
+
+    // Number of values called for each pattern (e.g. called_in_pattern[2] = 3
+    // means that pattern 2 has 3 cells that have been called).
+    called_in_pattern: Vec<usize>,
 
-    // Ditto, but for the columns.
-    called_in_col: [usize; BOARD_COLS]
+    // For each cell (indexed by row * cols + col), the indices of the
+    // patterns containing that cell.
+    patterns_at_cell: Vec<Vec<usize>>
+}
+
+// Reasons a board could not be read from stdin.
+#[derive(Debug)]
+enum BoardError {
+    // The input ended partway through a row of the board.
+    Truncated { line: usize },
+    // A row has a different number of columns than the rows before it.
+    Ragged { line: usize, cols: Col, expected: Col },
+    // The board doesn't have the shape given by --card-size.
+    Shape { line: usize, rows: Row, cols: Col, expected: (Row, Col) },
+    // Reading from stdin failed.
+    Read { line: usize, error: std::io::Error },
+}
+
+impl std::fmt::Display for BoardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BoardError::Truncated { line } =>
+                write!(f, "line {}: input ends partway through a board", line),
+            BoardError::Ragged { line, cols, expected } =>
+                write!(f, "line {}: board row has {} cols, but previous rows had {} cols", line, cols, expected),
+            BoardError::Shape { line, rows, cols, expected } =>
+                write!(f, "line {}: board is {}x{}, but --card-size is {}x{}", line, rows, cols, expected.0, expected.1),
+            BoardError::Read { line, error } =>
+                write!(f, "line {}: failed to read from stdin: {}", line, error),
+        }
+    }
 }
 
 impl Board {
-    // Adds row data to a new board.
-    fn add_row(&mut self, row: Row, row_vals: Vec<Num>) {
-        assert!(row < BOARD_ROWS);
-        assert!(row_vals.len() == BOARD_COLS);
+    // Adds row data to a new board. The first row decides how many
+    // columns the board has; every later row must match it. Returns the
+    // expected number of columns if the row doesn't match.
+    fn add_row(&mut self, row_vals: Vec<Num>) -> Result<(), Col> {
+        if self.rows == 0 {
+            self.cols = row_vals.len();
+        }
+        else if row_vals.len() != self.cols {
+            return Err(self.cols);
+        }
         for (col, val) in row_vals.iter().enumerate() {
-            self.layout.push((*val, row, col));
+            self.layout.push((*val, self.rows, col));
             self.remaining.push(*val);
         }
+        self.rows += 1;
+        Ok(())
     }
 
-    // Finalizes the new board by sorting the values for easy indexing.
-    fn sort_values(&mut self) {
+    // Finalizes the new board by sorting the values for easy indexing,
+    // and generating the winning patterns now that the shape is known.
+    fn finalize(&mut self, win_rules: &WinRules) {
         self.layout.sort_unstable_by_key(|nrc|{ nrc.0 });
         self.remaining.sort_unstable();
+        self.patterns = win_rules.patterns(self.rows, self.cols);
+        self.called_in_pattern = vec![0; self.patterns.len()];
+        self.patterns_at_cell = vec![Vec::new(); self.rows * self.cols];
+        for (index, pattern) in self.patterns.iter().enumerate() {
+            for &(row, col) in pattern.cells.iter() {
+                self.patterns_at_cell[row * self.cols + col].push(index);
+            }
+        }
     }
 
-    // Read a full board from stdin. If nothing to parse, return None.
-    // If parsing stops mid-board, panic!
-    fn new_from_stdin() -> Option<Self> {
+    // Read a full board from stdin. A board is a block of consecutive
+    // non-empty lines, ended by an empty line or EOF. If the input ends
+    // cleanly before another board starts, return Ok(None).
+    //
+    // A row that is shorter than the rows before it is reported as
+    // truncated if it is the last line of the input (no newline after it),
+    // and as ragged otherwise. Boards can have any shape, unless an
+    // `expected` shape is given (by --card-size). Then a board with fewer
+    // rows that ends at EOF is reported as truncated, and any other board
+    // of a different shape as the wrong shape.
+    //
+    // line_number counts the lines read from stdin so far, and is used to
+    // report errors and warn about values that appear more than once on
+    // the board.
+    fn new_from_stdin(win_rules: &WinRules, expected: Option<(Row, Col)>, line_number: &mut usize)
+        -> Result<Option<Self>, BoardError> {
         
         // Fill in this board object
         let mut board = Board{
+            rows: 0,
+            cols: 0,
             layout: Vec::new(),
             remaining: Vec::new(),
-            called_in_row: [0; BOARD_ROWS],
-            called_in_col: [0; BOARD_COLS]
+            patterns: Vec::new(),
+            called_in_pattern: Vec::new(),
+            patterns_at_cell: Vec::new()
         };
 
+        // Line on which each value first appeared on this board.
+        let mut seen: HashMap<Num, usize> = HashMap::new();
+
         // Read lines from stdin
-        let mut row: Row = 0;
-        while row < BOARD_ROWS {
+        loop {
             let mut line = String::new();
             match std::io::stdin().read_line(&mut line) {
-                Err(_) | Ok(0) => return None,
+                Err(error) => return Err(BoardError::Read { line: *line_number + 1, error }),
+                Ok(0) => {
+                    if let Some(expected) = expected {
+                        if board.rows > 0 && board.rows < expected.0 {
+                            return Err(BoardError::Truncated { line: *line_number });
+                        }
+                    }
+                    break;
+                }
                 Ok(_) => {
+                    *line_number += 1;
                     let trimmed = line.trim();
                     if trimmed.is_empty() {
-                        if row == 0 {
+                        if board.rows == 0 {
                             continue;
                         }
                         else {
-                            panic!("Encountered incomplete board from stdin");
+                            break;
                         }
                     }
                     // Parse line and add it as a new row to the board.
                     let row_vals: Vec<Num> = trimmed.split_ascii_whitespace().map(parse_num).collect();
-                    board.add_row(row, row_vals);
-                    row += 1;
+                    for val in row_vals.iter() {
+                        match seen.entry(*val) {
+                            Entry::Occupied(first_line) => eprintln!(
+                                "Warning: line {}: {} already appears on this board (line {}); every cell holding it is marked together",
+                                line_number, val, first_line.get()),
+                            Entry::Vacant(slot) => {
+                                slot.insert(*line_number);
+                            }
+                        }
+                    }
+                    let cols = row_vals.len();
+                    if let Err(expected) = board.add_row(row_vals) {
+                        if cols < expected && !line.ends_with('\n') {
+                            return Err(BoardError::Truncated { line: *line_number });
+                        }
+                        return Err(BoardError::Ragged { line: *line_number, cols, expected });
+                    }
                 }
             }
         }
-        board.sort_values();
-        return Some(board);
-    }
-
-    // Mark this number of the board. If it resulted in a BINGO! then
-    // return Some(score); otherwise, return None.
-    fn on_called_number(&mut self, num: Num) -> Option<Score> {
-        // Find the value in the layout.
-        if let Ok(index) = self.layout.binary_search_by_key(&num, |nrc|{ nrc.0 }) {
-            self.remaining.remove(self.remaining.binary_search(&num).expect("Not found in remaining"));
-            let (_, row, col) = self.layout[index];
-            self.called_in_row[row] += 1;
-            self.called_in_col[col] += 1;
-            if self.called_in_row[row] == BOARD_COLS || self.called_in_col[col] == BOARD_ROWS{
-                // BINGO!
-                return Some(self.score(num));
+        if board.rows == 0 {
+            return Ok(None);
+        }
+        if let Some(expected) = expected {
+            if (board.rows, board.cols) != expected {
+                return Err(BoardError::Shape { line: *line_number, rows: board.rows, cols: board.cols, expected });
             }
         }
+        board.finalize(win_rules);
+        Ok(Some(board))
+    }
+
+    // Mark this number of the board. Every cell holding the number is
+    // marked, and calling a number that was already called does nothing.
+    // If it resulted in a BINGO! then return Some((score, pattern)) naming
+    // the pattern that completed; otherwise, return None.
+    fn on_called_number(&mut self, num: Num) -> Option<(Score, &Pattern)> {
+        // Remove every copy of the value from the remaining values. If there
+        // are none, the value isn't on the board or was already called.
+        let start = self.remaining.partition_point(|&val| val < num);
+        let end = self.remaining.partition_point(|&val| val <= num);
+        if start == end {
+            return None;
+        }
+        self.remaining.drain(start..end);
+
+        // Find the value in the layout, and mark each cell holding it.
+        let start = self.layout.partition_point(|nrc| nrc.0 < num);
+        let end = self.layout.partition_point(|nrc| nrc.0 <= num);
+        let mut completed: Option<usize> = None;
+        for &(_, row, col) in self.layout[start..end].iter() {
+            for &pattern in self.patterns_at_cell[row * self.cols + col].iter() {
+                self.called_in_pattern[pattern] += 1;
+                if self.called_in_pattern[pattern] == self.patterns[pattern].cells.len() {
+                    completed = Some(completed.map_or(pattern, |c| std::cmp::min(c, pattern)));
+                }
+            }
+        }
+        if let Some(pattern) = completed {
+            // BINGO!
+            return Some((self.score(num), &self.patterns[pattern]));
+        }
         None
     }
 
+    // Predicts when this board wins without calling any numbers. A pattern
+    // completes on the latest turn of any of its cells, and the board wins
+    // on the earliest turn of any of its patterns. Returns None if the
+    // board never wins.
+    fn predict_win(&self, turn_of: &HashMap<Num, Turn>, called_numbers: &[Num]) -> Option<Prediction> {
+        // Turn on which each cell is called (indexed by row * cols + col).
+        let mut cell_turns: Vec<Option<Turn>> = vec![None; self.rows * self.cols];
+        for &(val, row, col) in self.layout.iter() {
+            cell_turns[row * self.cols + col] = turn_of.get(&val).copied();
+        }
+
+        let mut best: Option<(Turn, usize)> = None;
+        for (index, pattern) in self.patterns.iter().enumerate() {
+            let completed_on: Option<Turn> = pattern.cells.iter()
+                .map(|&(row, col)| cell_turns[row * self.cols + col])
+                .try_fold(0, |latest, turn| Some(std::cmp::max(latest, turn?)));
+            if let Some(turn) = completed_on {
+                if best.is_none_or(|(best_turn, _)| turn < best_turn) {
+                    best = Some((turn, index));
+                }
+            }
+        }
+
+        let (turn, pattern) = best?;
+        let last_called = called_numbers[turn - 1];
+        let unmarked: Score = self.layout.iter()
+            .filter(|&&(val, _, _)| turn_of.get(&val).is_none_or(|&t| t > turn))
+            .map(|&(val, _, _)| val)
+            .sum();
+        Some(Prediction {
+            turn,
+            score: unmarked * last_called,
+            pattern: self.patterns[pattern].name.clone(),
+        })
+    }
+
     fn score(&self, last_called: Num) -> Score {
         let mut s: Score = 0;
         for val in &self.remaining {
             s += val;
         }
-        return s * last_called;
+        s * last_called
+    }
+}
+
+// The outcome of one board, either predicted or simulated.
+#[derive(Debug, PartialEq)]
+struct Prediction {
+    turn: Turn,
+    score: Score,
+    pattern: String,
+}
+
+// Maps each called number to the first turn on which it is called. Later
+// calls of the same number have no effect, so they are left out (with a
+// warning, since the called numbers are on line 1).
+fn turn_index(called_numbers: &[Num]) -> HashMap<Num, Turn> {
+    let mut turn_of: HashMap<Num, Turn> = HashMap::new();
+    for (index, &num) in called_numbers.iter().enumerate() {
+        if let Some(&first_turn) = turn_of.get(&num) {
+            eprintln!(
+                "Warning: line 1: {} is called again on turn {} (first called on turn {}); the repeat is ignored",
+                num, index + 1, first_turn);
+        }
+        else {
+            turn_of.insert(num, index + 1);
+        }
+    }
+    turn_of
+}
+
+// Options given on the command line.
+struct Options {
+    win_rules: WinRules,
+    replay: bool, // Print every board after each call
+    step: bool,   // Step through the replay interactively
+    generate: Option<usize>, // Print this many random cards instead of playing
+    simulate: Option<usize>, // Play this many random games on the input cards
+    size: (Row, Col),        // Shape of generated cards
+    card_size: Option<(Row, Col)>, // Shape every input card must have
+    max: Option<Num>,        // Largest number on generated cards and calls
+    seed: u64,               // Seed for generating cards and calls
+}
+
+// Reads the options from the command line:
+//   --win rows,cols,diagonals,corners,x,full   Enable these rules (default: rows,cols)
+//   --masks FILE                               Also win on the masks in FILE
+//   --replay                                   Print every board after each call
+//   --step                                     Step forward and back through the replay
+//   --generate COUNT                           Print COUNT random cards and a call sequence
+//   --simulate GAMES                           Estimate each card's odds over GAMES random games
+//   --size RxC                                 Shape of generated cards (default: 5x5)
+//   --card-size RxC                            Require every input card to have this shape
+//                                              (default: any shape)
+//   --max N                                    Numbers range from 0 to N (default: 99 when
+//                                              generating, the largest card value when simulating)
+//   --seed S                                   Seed for the random numbers (default: 0)
+fn parse_args() -> Options {
+    let mut options = Options {
+        win_rules: WinRules::new(),
+        replay: false,
+        step: false,
+        generate: None,
+        simulate: None,
+        size: (5, 5),
+        card_size: None,
+        max: None,
+        seed: 0,
+    };
+    let win_rules = &mut options.win_rules;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--win" => {
+                let names = args.next().expect("--win requires a list of rules");
+                win_rules.rules = names.split(',').map(WinRule::parse).collect();
+            }
+            "--masks" => {
+                let path = args.next().expect("--masks requires a file name");
+                win_rules.load_masks(&path);
+            }
+            "--replay" => options.replay = true,
+            "--step" => options.step = true,
+            "--generate" => options.generate = Some(parse_arg(&arg, args.next())),
+            "--simulate" => options.simulate = Some(parse_arg(&arg, args.next())),
+            "--max" => {
+                let max: Num = parse_arg(&arg, args.next());
+                assert!(max >= 0, "--max must not be negative: {}", max);
+                options.max = Some(max);
+            }
+            "--seed" => options.seed = parse_arg(&arg, args.next()),
+            "--size" => options.size = parse_shape(&arg, args.next()),
+            "--card-size" => options.card_size = Some(parse_shape(&arg, args.next())),
+            _ => panic!("Unknown argument: {}", arg),
+        }
     }
+    options
+}
+
+// Parses a shape like 5x5 following a command line flag.
+fn parse_shape(flag: &str, value: Option<String>) -> (Row, Col) {
+    let value = value.unwrap_or_else(|| panic!("{} requires a shape, like 5x5", flag));
+    let (rows, cols) = value.split_once('x').unwrap_or_else(|| panic!("{} must look like 5x5", flag));
+    (parse_arg(flag, Some(rows.to_string())), parse_arg(flag, Some(cols.to_string())))
+}
+
+// Parses the value following a command line flag.
+fn parse_arg<T: std::str::FromStr>(flag: &str, value: Option<String>) -> T {
+    let value = value.unwrap_or_else(|| panic!("{} requires a value", flag));
+    value.parse().unwrap_or_else(|_| panic!("Invalid value for {}: {}", flag, value))
 }
 
 fn main() {
+    let options = parse_args();
+    let win_rules = &options.win_rules;
+    let mut rng = simulate::Rng::new(options.seed);
+
+    // Generating cards doesn't need any input.
+    if let Some(count) = options.generate {
+        let (rows, cols) = options.size;
+        simulate::print_puzzle(&mut rng, count, rows, cols, options.max.unwrap_or(99));
+        return;
+    }
+
     // Read numbers that are called.
     let mut line = String::new();
     std::io::stdin().read_line(&mut line).expect("Could not read called numbers. EOF");
@@ -119,23 +400,81 @@ fn main() {
 
     // Read all the boards.
     let mut board_id: i32 = 0;
+    let mut line_number: usize = 1;
     let mut boards: Vec<(i32, Box<Board>)> = Vec::new();
-    while let Some(board) = Board::new_from_stdin() {
-        boards.push((board_id, Box::new(board)));
-        board_id += 1;
+    loop {
+        match Board::new_from_stdin(win_rules, options.card_size, &mut line_number) {
+            Ok(Some(board)) => {
+                boards.push((board_id, Box::new(board)));
+                board_id += 1;
+            }
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("Error reading board {}: {}", board_id, e);
+                std::process::exit(1);
+            }
+        }
     }
     println!("Read {} boards", boards.len());
+    for (id, board) in boards.iter() {
+        println!("Board {} is {}x{}", id, board.rows, board.cols);
+    }
+
+    // Simulate random games on the cards instead of playing the input's.
+    if let Some(games) = options.simulate {
+        let largest = boards.iter().filter_map(|(_, board)| board.layout.last()).map(|nrc| nrc.0).max();
+        let max = options.max.or(largest).expect("No cards to simulate");
+        simulate::run(&boards, games, max, &mut rng);
+        return;
+    }
+
+    // Replay the game on copies of the boards before the real one starts
+    // removing the winners.
+    if options.replay || options.step {
+        let frames = replay::record(&boards, &called_numbers);
+        if options.step {
+            replay::step(&frames);
+        }
+        else {
+            replay::play(&frames);
+        }
+    }
+
+    // Predict every board's winning turn up front, then rank the boards
+    // from first to last winner. Boards that win on the same turn stay in
+    // input order, the same order the simulation reports them in.
+    let turn_of = turn_index(&called_numbers);
+    let mut predicted: Vec<(i32, Prediction)> = boards.iter()
+        .filter_map(|(id, board)| Some((*id, board.predict_win(&turn_of, &called_numbers)?)))
+        .collect();
+    predicted.sort_by_key(|(_, prediction)| prediction.turn);
 
     // Start calling numbers until a board gets a bingo.
-    for num in called_numbers {
+    let mut simulated: Vec<(i32, Prediction)> = Vec::new();
+    for (index, num) in called_numbers.iter().enumerate() {
         println!("Calling {}", num);
         boards.retain_mut(|item| -> bool {
             let (id, board) = item;
-            if let Some(score) = board.on_called_number(num) {
-                println!("BINGO! on board {} with score {}", id, score);
+            if let Some((score, pattern)) = board.on_called_number(*num) {
+                println!("BINGO! on board {} with score {} ({})", id, score, pattern.name);
+                simulated.push((*id, Prediction { turn: index + 1, score, pattern: pattern.name.clone() }));
                 return false;
             }
-            return true;
+            true
         });
     }
+
+    // The prediction must agree with the simulation, board for board.
+    assert_eq!(predicted, simulated, "Predicted winners differ from the simulation");
+    for (place, (id, prediction)) in predicted.iter().enumerate() {
+        println!(
+            "Rank {}: board {} wins on turn {} with score {} ({})",
+            place + 1, id, prediction.turn, prediction.score, prediction.pattern);
+    }
+    if let Some((id, first)) = predicted.first() {
+        println!("Part 1: board {} wins first, on turn {}, with score {}", id, first.turn, first.score);
+    }
+    if let Some((id, last)) = predicted.last() {
+        println!("Part 2: board {} wins last, on turn {}, with score {}", id, last.turn, last.score);
+    }
 }
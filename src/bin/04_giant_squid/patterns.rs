@@ -0,0 +1,163 @@
+use crate::{Col, Row};
+
+// A set of cells that wins the game once every one of them is called.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    pub name: String,
+    pub cells: Vec<(Row, Col)>,
+}
+
+impl Pattern {
+    fn new(name: String, mut cells: Vec<(Row, Col)>) -> Self {
+        // A cell may be listed twice (e.g. the center of an X), but it
+        // only needs to be called once.
+        cells.sort_unstable();
+        cells.dedup();
+        Pattern { name, cells }
+    }
+}
+
+// The kinds of winning patterns that can be enabled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WinRule {
+    Rows,      // Any full row
+    Cols,      // Any full column
+    Diagonals, // Either full diagonal (square boards only)
+    Corners,   // The four corner cells
+    XShape,    // Both diagonals at once (square boards only)
+    FullCard,  // Every cell on the board
+}
+
+impl WinRule {
+    pub fn parse(name: &str) -> Self {
+        match name {
+            "rows" => WinRule::Rows,
+            "cols" => WinRule::Cols,
+            "diagonals" => WinRule::Diagonals,
+            "corners" => WinRule::Corners,
+            "x" => WinRule::XShape,
+            "full" => WinRule::FullCard,
+            _ => panic!("Unknown win rule: {} (expected rows, cols, diagonals, corners, x or full)", name),
+        }
+    }
+
+    // Generate the patterns for this rule on a board of the given shape.
+    fn patterns(&self, rows: Row, cols: Col) -> Vec<Pattern> {
+        let diag = |n: usize| -> Vec<(Row, Col)> { (0..n).map(|i| (i, i)).collect() };
+        let anti_diag = |n: usize| -> Vec<(Row, Col)> { (0..n).map(|i| (i, n - 1 - i)).collect() };
+        match self {
+            WinRule::Rows => (0..rows)
+                .map(|row| Pattern::new(format!("row {}", row), (0..cols).map(|col| (row, col)).collect()))
+                .collect(),
+            WinRule::Cols => (0..cols)
+                .map(|col| Pattern::new(format!("col {}", col), (0..rows).map(|row| (row, col)).collect()))
+                .collect(),
+            WinRule::Diagonals if rows == cols => vec![
+                Pattern::new(String::from("diagonal"), diag(rows)),
+                Pattern::new(String::from("anti-diagonal"), anti_diag(rows)),
+            ],
+            WinRule::Corners => vec![Pattern::new(
+                String::from("four corners"),
+                vec![(0, 0), (0, cols - 1), (rows - 1, 0), (rows - 1, cols - 1)],
+            )],
+            WinRule::XShape if rows == cols => {
+                let mut cells = diag(rows);
+                cells.extend(anti_diag(rows));
+                vec![Pattern::new(String::from("X"), cells)]
+            }
+            WinRule::FullCard => vec![Pattern::new(
+                String::from("full card"),
+                (0..rows).flat_map(|row| (0..cols).map(move |col| (row, col))).collect(),
+            )],
+            WinRule::Diagonals | WinRule::XShape => Vec::new(),
+        }
+    }
+}
+
+// A pattern loaded from a mask file. It only applies to boards with the
+// same shape as the mask.
+#[derive(Debug)]
+pub struct Mask {
+    rows: Row,
+    cols: Col,
+    pattern: Pattern,
+}
+
+// Every pattern that wins the game: the enabled rules plus any masks.
+#[derive(Debug)]
+pub struct WinRules {
+    pub rules: Vec<WinRule>,
+    pub masks: Vec<Mask>,
+}
+
+impl WinRules {
+    // The classic rules: any full row or column.
+    pub fn new() -> Self {
+        WinRules {
+            rules: vec![WinRule::Rows, WinRule::Cols],
+            masks: Vec::new(),
+        }
+    }
+
+    // Reads masks from a file. Each mask is a block of lines separated by
+    // empty lines. In each line, '#' marks a cell of the pattern and '.'
+    // marks a cell that isn't part of it. For example, a 3x3 "plus" is:
+    //
+    //     .#.
+    //     ###
+    //     .#.
+    pub fn load_masks(&mut self, path: &str) {
+        let contents = std::fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read mask file {}: {}", path, e));
+        let mut block: Vec<&str> = Vec::new();
+        for line in contents.lines().map(|l| l.trim()).chain(std::iter::once("")) {
+            if !line.is_empty() {
+                block.push(line);
+            }
+            else if !block.is_empty() {
+                let name = format!("mask {}", self.masks.len());
+                self.masks.push(parse_mask(name, &block));
+                block.clear();
+            }
+        }
+    }
+
+    // Generate every winning pattern for a board of the given shape.
+    pub fn patterns(&self, rows: Row, cols: Col) -> Vec<Pattern> {
+        let mut result: Vec<Pattern> = Vec::new();
+        for rule in self.rules.iter() {
+            result.extend(rule.patterns(rows, cols));
+        }
+        for mask in self.masks.iter() {
+            if mask.rows == rows && mask.cols == cols {
+                result.push(mask.pattern.clone());
+            }
+        }
+        result
+    }
+}
+
+fn parse_mask(name: String, block: &[&str]) -> Mask {
+    let cols = block[0].len();
+    let mut cells: Vec<(Row, Col)> = Vec::new();
+    for (row, line) in block.iter().enumerate() {
+        if line.len() != cols {
+            panic!("Encountered {} row with {} cols, but previous rows had {} cols.", name, line.len(), cols);
+        }
+        for (col, ch) in line.chars().enumerate() {
+            match ch {
+                '#' => cells.push((row, col)),
+                '.' => {}
+                _ => panic!("Invalid character in {}: {}", name, ch),
+            }
+        }
+    }
+    if cells.is_empty() {
+        panic!("{} does not mark any cells", name);
+    }
+    Mask {
+        rows: block.len(),
+        cols,
+        pattern: Pattern::new(name, cells),
+    }
+}
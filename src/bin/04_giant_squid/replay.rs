@@ -0,0 +1,131 @@
+use crate::{Board, Num};
+use std::io::{BufRead, Write};
+
+// ANSI escape codes used to highlight cells.
+const MARKED: &str = "\x1b[1;32m";        // Bold green
+const JUST_CALLED: &str = "\x1b[1;30;43m"; // Bold black on yellow
+const BINGO: &str = "\x1b[1;31m";         // Bold red
+const RESET: &str = "\x1b[0m";
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+impl Board {
+    // A cell is marked once its value is no longer remaining.
+    fn is_marked(&self, val: Num) -> bool {
+        self.remaining.binary_search(&val).is_err()
+    }
+
+    // Renders the board as text, highlighting the marked cells and the
+    // cells holding the number that was just called.
+    fn render(&self, just_called: Option<Num>) -> String {
+        let mut grid: Vec<Num> = vec![0; self.rows * self.cols];
+        for &(val, row, col) in self.layout.iter() {
+            grid[row * self.cols + col] = val;
+        }
+        let width = grid.iter().map(|val| val.to_string().len()).max().unwrap_or(0);
+
+        let mut out = String::new();
+        for row in grid.chunks(self.cols) {
+            for (col, &val) in row.iter().enumerate() {
+                if col > 0 {
+                    out.push(' ');
+                }
+                let cell = format!("{:>width$}", val, width = width);
+                if Some(val) == just_called {
+                    out.push_str(&format!("{}{}{}", JUST_CALLED, cell, RESET));
+                }
+                else if self.is_marked(val) {
+                    out.push_str(&format!("{}{}{}", MARKED, cell, RESET));
+                }
+                else {
+                    out.push_str(&cell);
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+// Plays the whole game on copies of the boards, and renders every board
+// after each call. Frame 0 shows the boards before any number is called;
+// frame N shows them after the Nth call. A board stops being marked once
+// it has won, as in the real game.
+pub fn record(boards: &[(i32, Box<Board>)], called_numbers: &[Num]) -> Vec<String> {
+    let mut boards: Vec<(i32, Box<Board>, Option<String>)> = boards.iter()
+        .map(|(id, board)| (*id, board.clone(), None))
+        .collect();
+    let mut frames: Vec<String> = vec![render_frame(&boards, 0, None)];
+    for (index, &num) in called_numbers.iter().enumerate() {
+        for (id, board, won) in boards.iter_mut() {
+            if won.is_some() {
+                continue;
+            }
+            if let Some((score, pattern)) = board.on_called_number(num) {
+                *won = Some(format!("BINGO! on board {} with score {} ({})", id, score, pattern.name));
+            }
+        }
+        frames.push(render_frame(&boards, index + 1, Some(num)));
+    }
+    frames
+}
+
+fn render_frame(boards: &[(i32, Box<Board>, Option<String>)], turn: usize, just_called: Option<Num>) -> String {
+    let mut out = String::new();
+    match just_called {
+        Some(num) => out.push_str(&format!("Turn {}: calling {}\n\n", turn, num)),
+        None => out.push_str("Turn 0: no numbers called yet\n\n"),
+    }
+    for (id, board, won) in boards.iter() {
+        out.push_str(&format!("Board {} ({}x{})", id, board.rows, board.cols));
+        if let Some(message) = won {
+            out.push_str(&format!("  {}{}{}", BINGO, message, RESET));
+        }
+        out.push('\n');
+        out.push_str(&board.render(just_called));
+        out.push('\n');
+    }
+    out
+}
+
+// Prints every frame, one after the other.
+pub fn play(frames: &[String]) {
+    for frame in frames {
+        print!("{}", frame);
+    }
+}
+
+// Shows one frame at a time, and lets the user step through them. Since
+// stdin holds the puzzle input, the commands are read from the terminal.
+pub fn step(frames: &[String]) {
+    let terminal = if cfg!(windows) { "CON" } else { "/dev/tty" };
+    let terminal = match std::fs::File::open(terminal) {
+        Ok(file) => std::io::BufReader::new(file),
+        Err(e) => {
+            eprintln!("Cannot read commands from the terminal ({}); printing every frame instead", e);
+            play(frames);
+            return;
+        }
+    };
+    let mut commands = terminal.lines();
+    let last = frames.len() - 1;
+    let mut current = 0;
+    loop {
+        print!("{}{}", CLEAR_SCREEN, frames[current]);
+        print!("[n]ext, [p]rev, [q]uit, or a turn number (0-{}): ", last);
+        std::io::stdout().flush().expect("Failed to flush stdout");
+        let command = match commands.next() {
+            Some(Ok(command)) => command,
+            _ => break,
+        };
+        match command.trim() {
+            "" | "n" => current = std::cmp::min(current + 1, last),
+            "p" => current = current.saturating_sub(1),
+            "q" => break,
+            turn => match turn.parse::<usize>() {
+                Ok(turn) if turn <= last => current = turn,
+                _ => continue,
+            },
+        }
+    }
+    println!();
+}
@@ -0,0 +1,124 @@
+use crate::{Board, Col, Num, Row, Score, Turn};
+
+// A small, seedable pseudo-random number generator (SplitMix64). It is
+// plenty for shuffling cards, and the same seed always gives the same games.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // A value in 0..n.
+    fn below(&mut self, n: usize) -> usize {
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+
+    // Shuffles the values in place (Fisher-Yates).
+    fn shuffle(&mut self, values: &mut [Num]) {
+        for i in (1..values.len()).rev() {
+            values.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+// A random call sequence: every number from 0 to max, each called once.
+pub fn generate_calls(rng: &mut Rng, max: Num) -> Vec<Num> {
+    let mut calls: Vec<Num> = (0..=max).collect();
+    rng.shuffle(&mut calls);
+    calls
+}
+
+// A random card of the given shape. Its values are distinct and drawn from
+// 0 to max, so every card can be completed by a full call sequence.
+pub fn generate_card(rng: &mut Rng, rows: Row, cols: Col, max: Num) -> Vec<Vec<Num>> {
+    assert!(
+        max >= 0 && rows * cols <= max as usize + 1,
+        "Cannot fill a {}x{} card with distinct numbers from 0 to {}", rows, cols, max);
+    let pool = generate_calls(rng, max);
+    pool[..rows * cols].chunks(cols).map(|row| row.to_vec()).collect()
+}
+
+// Prints a whole puzzle input: a call sequence followed by the cards, in
+// the same format this program reads.
+pub fn print_puzzle(rng: &mut Rng, cards: usize, rows: Row, cols: Col, max: Num) {
+    let calls: Vec<String> = generate_calls(rng, max).iter().map(|n| n.to_string()).collect();
+    println!("{}", calls.join(","));
+    let width = max.to_string().len();
+    for _ in 0..cards {
+        println!();
+        for row in generate_card(rng, rows, cols, max) {
+            let vals: Vec<String> = row.iter().map(|n| format!("{:>width$}", n, width = width)).collect();
+            println!("{}", vals.join(" "));
+        }
+    }
+}
+
+// What happened to one card over all the simulated games.
+#[derive(Default)]
+struct CardStats {
+    first_wins: usize, // Games in which this card won first (ties included)
+    bingos: usize,     // Games in which this card got a bingo at all
+    turn_sum: usize,   // Sum of the turns on which it got a bingo
+    score_sum: i64,    // Sum of the scores it got a bingo with
+}
+
+// Plays many games on the given cards, each with a random call sequence
+// of every number from 0 to max, and prints per-card statistics: how
+// often the card wins first, and its expected bingo turn and score.
+pub fn run(boards: &[(i32, Box<Board>)], games: usize, max: Num, rng: &mut Rng) {
+    let mut stats: Vec<CardStats> = boards.iter().map(|_| CardStats::default()).collect();
+    for _ in 0..games {
+        let calls = generate_calls(rng, max);
+        let mut playing: Vec<Board> = boards.iter().map(|(_, board)| (**board).clone()).collect();
+        let mut won: Vec<Option<(Turn, Score)>> = vec![None; playing.len()];
+        for (index, &num) in calls.iter().enumerate() {
+            for (board, result) in playing.iter_mut().zip(won.iter_mut()) {
+                if result.is_none() {
+                    if let Some((score, _)) = board.on_called_number(num) {
+                        *result = Some((index + 1, score));
+                    }
+                }
+            }
+            if won.iter().all(|result| result.is_some()) {
+                break;
+            }
+        }
+
+        let first_turn = won.iter().flatten().map(|&(turn, _)| turn).min();
+        for (card, result) in stats.iter_mut().zip(won.iter()) {
+            if let Some((turn, score)) = *result {
+                card.bingos += 1;
+                card.turn_sum += turn;
+                card.score_sum += score as i64;
+                if Some(turn) == first_turn {
+                    card.first_wins += 1;
+                }
+            }
+        }
+    }
+
+    println!("Simulated {} games with numbers 0 to {}", games, max);
+    for ((id, _), card) in boards.iter().zip(stats.iter()) {
+        if card.bingos == 0 {
+            println!("Board {}: never wins", id);
+            continue;
+        }
+        println!(
+            "Board {}: wins first {:.2}% of games, expected bingo on turn {:.2} with score {:.2}",
+            id,
+            100.0 * card.first_wins as f64 / games as f64,
+            card.turn_sum as f64 / card.bingos as f64,
+            card.score_sum as f64 / card.bingos as f64);
+    }
+}
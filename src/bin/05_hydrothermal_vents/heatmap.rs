@@ -0,0 +1,101 @@
+use crate::{Coord, Count, Line, VentMap, MAX_DENSE_CELLS};
+use std::io::Write;
+
+// Maps a count to a colour, from black (no lines) through red and yellow
+// to white (the most crossed points).
+fn heat_colour(count: Count, max: Count) -> (u8, u8, u8) {
+    if count == 0 || max == 0 {
+        return (0, 0, 0);
+    }
+    let t = 3.0 * count as f64 / max as f64;
+    let channel = |v: f64| (255.0 * v.clamp(0.0, 1.0)).round() as u8;
+    (channel(t), channel(t - 1.0), channel(t - 2.0))
+}
+
+fn create(path: &str) -> std::io::Result<std::io::BufWriter<std::fs::File>> {
+    Ok(std::io::BufWriter::new(std::fs::File::create(path)?))
+}
+
+// Writes the overlap counts as an image, with one pixel per point. A .pgm
+// file is greyscale, scaled so the most crossed point is white; a .ppm file
+// uses the heat colour scale. An .svg file additionally has a legend for
+// the colour scale, and draws the lines on top if `overlay` is set.
+pub fn write(vm: &VentMap, lines: &[Line], path: &str, overlay: bool) -> std::io::Result<()> {
+    let max = vm.max_count();
+    if path.ends_with(".svg") {
+        return write_svg(vm, lines, path, overlay, max);
+    }
+    assert!(!overlay, "Line overlays are only supported for .svg files");
+    let greyscale = path.ends_with(".pgm");
+    assert!(greyscale || path.ends_with(".ppm"), "Heatmap file must end in .pgm, .ppm or .svg: {}", path);
+    assert!(
+        (vm.x_size as u64) * (vm.y_size as u64) <= MAX_DENSE_CELLS,
+        "Map is too large for a {}x{} image; use .svg instead", vm.x_size, vm.y_size);
+
+    let mut out = create(path)?;
+    let magic = if greyscale { "P5" } else { "P6" };
+    write!(out, "{}\n{} {}\n255\n", magic, vm.x_size, vm.y_size)?;
+    for y in 0..vm.y_size as Coord {
+        for x in 0..vm.x_size as Coord {
+            let count = vm.get(vm.x_min + x, vm.y_min + y);
+            if greyscale {
+                let grey = if max == 0 { 0 } else { (255 * count as u64 / max as u64) as u8 };
+                out.write_all(&[grey])?;
+            }
+            else {
+                let (r, g, b) = heat_colour(count, max);
+                out.write_all(&[r, g, b])?;
+            }
+        }
+    }
+    out.flush()
+}
+
+// Only the crossed points are drawn, so this also works for sparse maps.
+fn write_svg(vm: &VentMap, lines: &[Line], path: &str, overlay: bool, max: Count) -> std::io::Result<()> {
+    let mut out = create(path)?;
+    let (width, height) = (vm.x_size as Coord, vm.y_size as Coord);
+    let legend = std::cmp::max(width, height) / 20 + 1; // Height of the legend bar
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}" shape-rendering="crispEdges">"#,
+        vm.x_min, vm.y_min, width, height + 3 * legend)?;
+    writeln!(out, r#"<rect x="{}" y="{}" width="{}" height="{}" fill="black"/>"#, vm.x_min, vm.y_min, width, height)?;
+
+    for ((x, y), count) in vm.crossed_points() {
+        let (r, g, b) = heat_colour(count, max);
+        writeln!(out, r#"<rect x="{}" y="{}" width="1" height="1" fill="rgb({},{},{})"/>"#, x, y, r, g, b)?;
+    }
+
+    if overlay {
+        writeln!(out, r#"<g stroke="cyan" stroke-opacity="0.5" stroke-width="0.2">"#)?;
+        // Lines run through the centres of the points they cross.
+        let centre = |c: Coord| c as f64 + 0.5;
+        for line in lines {
+            writeln!(
+                out,
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}"/>"#,
+                centre(line.x1), centre(line.y1), centre(line.x2), centre(line.y2))?;
+        }
+        writeln!(out, "</g>")?;
+    }
+
+    // Legend: one swatch per count, from 1 up to the maximum.
+    let top = vm.y_min + height + legend / 2;
+    if max > 0 {
+        let swatch = width as f64 / max as f64;
+        for count in 1..=max {
+            let (r, g, b) = heat_colour(count, max);
+            writeln!(
+                out,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="rgb({},{},{})"/>"#,
+                vm.x_min as f64 + (count - 1) as f64 * swatch, top, swatch, legend, r, g, b)?;
+        }
+    }
+    writeln!(
+        out,
+        r#"<text x="{}" y="{}" font-size="{}" fill="black">1 to {} lines</text>"#,
+        vm.x_min, top + legend + legend / 2, legend / 2 + 1, max)?;
+    writeln!(out, "</svg>")?;
+    out.flush()
+}
@@ -0,0 +1,165 @@
+use crate::{gcd, Coord, Line, SlopeError, SlopeMode};
+use std::collections::{HashMap, HashSet};
+
+type Point = (Coord, Coord);
+
+// A carrier line: every lattice point p = base + k * dir. The direction is
+// primitive (its components share no factor) and points right, or up if
+// vertical. `offset` is cross(dir, p), which is the same for every point
+// on the line, so (dir, offset) identifies the line.
+type LineKey = (Point, i128);
+
+// A line of vents, as the range of lattice points it covers along its
+// carrier line. Positions are dot(dir, p); consecutive lattice points
+// are dot(dir, dir) apart.
+struct Segment {
+    start: Point,
+    dir: Point,
+    steps: Coord, // Number of steps from one end to the other
+    key: LineKey,
+    lo: i128,
+    hi: i128,
+}
+
+fn cross(a: Point, b: Point) -> i128 {
+    a.0 as i128 * b.1 as i128 - a.1 as i128 * b.0 as i128
+}
+
+fn dot(a: Point, b: Point) -> i128 {
+    a.0 as i128 * b.0 as i128 + a.1 as i128 * b.1 as i128
+}
+
+impl Segment {
+    fn new(line: &Line, mode: SlopeMode) -> Result<Self, SlopeError> {
+        let Line { x1, y1, x2, y2 } = *line;
+        let (dx, dy) = (x2 - x1, y2 - y1);
+        if !(dx == 0 || dy == 0 || dx.abs() == dy.abs() || mode == SlopeMode::Lattice) {
+            return Err(SlopeError { line: *line });
+        }
+        let steps = gcd(dx.abs(), dy.abs());
+        let mut dir = if steps == 0 { (1, 0) } else { (dx / steps, dy / steps) };
+        if dir.0 < 0 || (dir.0 == 0 && dir.1 < 0) {
+            dir = (-dir.0, -dir.1);
+        }
+        let (a, b) = (dot(dir, (x1, y1)), dot(dir, (x2, y2)));
+        Ok(Segment {
+            start: (x1, y1),
+            dir: (dx.signum() * dir.0.abs(), dy.signum() * dir.1.abs()),
+            steps,
+            key: (dir, cross(dir, (x1, y1))),
+            lo: std::cmp::min(a, b),
+            hi: std::cmp::max(a, b),
+        })
+    }
+
+    // Whether the segment covers the lattice point p.
+    fn contains(&self, p: Point) -> bool {
+        let (dir, offset) = self.key;
+        let pos = dot(dir, p);
+        cross(dir, p) == offset && self.lo <= pos && pos <= self.hi
+    }
+
+    // The lattice point where two segments on different carrier lines
+    // meet, if any.
+    fn crossing(&self, other: &Segment) -> Option<Point> {
+        // Solve start + t * dir = other.start + s * other.dir for integer
+        // steps t and s within both segments.
+        let denom = cross(self.dir, other.dir);
+        if denom == 0 {
+            return None;
+        }
+        let diff = (other.start.0 - self.start.0, other.start.1 - self.start.1);
+        let t_num = cross(diff, other.dir);
+        let s_num = cross(diff, self.dir);
+        if t_num % denom != 0 || s_num % denom != 0 {
+            return None;
+        }
+        let (t, s) = (t_num / denom, s_num / denom);
+        if t < 0 || t > self.steps as i128 || s < 0 || s > other.steps as i128 {
+            return None;
+        }
+        let t = t as Coord;
+        Some((self.start.0 + t * self.dir.0, self.start.1 + t * self.dir.1))
+    }
+}
+
+// Counts the lattice points covered by two or more lines, straight from
+// the lines themselves. Lines sharing a carrier line overlap on a range of
+// positions, found by sorting them along that line. Lines on different
+// carrier lines meet in at most one point, found by checking every pair.
+// Single points (lines whose ends are equal) are compared with everything.
+pub fn count_overlaps(lines: &[Line], mode: SlopeMode) -> Result<usize, SlopeError> {
+    let segments = lines.iter()
+        .map(|line| Segment::new(line, mode))
+        .collect::<Result<Vec<Segment>, SlopeError>>()?;
+
+    // Group the segments by carrier line.
+    let mut by_line: HashMap<LineKey, Vec<(i128, i128)>> = HashMap::new();
+    for segment in segments.iter().filter(|s| s.steps > 0) {
+        by_line.entry(segment.key).or_default().push((segment.lo, segment.hi));
+    }
+
+    // On each carrier line, find the ranges covered by two or more
+    // segments, merged so that no position is counted twice.
+    let mut overlaps: HashMap<LineKey, Vec<(i128, i128)>> = HashMap::new();
+    let mut count: usize = 0;
+    for (key, mut ranges) in by_line {
+        ranges.sort_unstable();
+        let spacing = dot(key.0, key.0);
+        let mut merged: Vec<(i128, i128)> = Vec::new();
+        let mut reach: Option<i128> = None; // Furthest position covered so far
+        for (lo, hi) in ranges {
+            if let Some(end) = reach {
+                if lo <= end {
+                    let overlap = (lo, std::cmp::min(hi, end));
+                    match merged.last_mut() {
+                        Some(last) if overlap.0 <= last.1 => last.1 = std::cmp::max(last.1, overlap.1),
+                        _ => merged.push(overlap),
+                    }
+                }
+            }
+            reach = Some(reach.map_or(hi, |end| std::cmp::max(end, hi)));
+        }
+        count += merged.iter().map(|(lo, hi)| ((hi - lo) / spacing + 1) as usize).sum::<usize>();
+        if !merged.is_empty() {
+            overlaps.insert(key, merged);
+        }
+    }
+
+    // Find the points where segments on different carrier lines meet.
+    let mut points: HashSet<Point> = HashSet::new();
+    for (i, a) in segments.iter().enumerate() {
+        for b in segments[i + 1..].iter() {
+            if a.steps == 0 || b.steps == 0 {
+                let (point, other) = if a.steps == 0 { (a, b) } else { (b, a) };
+                if other.contains(point.start) {
+                    points.insert(point.start);
+                }
+            }
+            else if a.key.0 != b.key.0 {
+                if let Some(p) = a.crossing(b) {
+                    points.insert(p);
+                }
+            }
+        }
+    }
+
+    // Every point where ranges on different carrier lines cross is also
+    // one of these points. Count each point exactly once: add the points
+    // outside every range, and remove the extra counts of points within
+    // ranges on several lines.
+    let directions: HashSet<Point> = overlaps.keys().map(|key| key.0).collect();
+    let ranges_containing = |p: Point| directions.iter().filter(|&&dir| {
+        overlaps.get(&(dir, cross(dir, p))).is_some_and(|ranges| {
+            let pos = dot(dir, p);
+            ranges.iter().any(|&(lo, hi)| lo <= pos && pos <= hi)
+        })
+    }).count();
+    for p in points {
+        match ranges_containing(p) {
+            0 => count += 1,
+            n => count -= n - 1,
+        }
+    }
+    Ok(count)
+}
@@ -1,97 +1,416 @@
+mod heatmap;
+mod intersect;
+mod queries;
+
+use std::collections::HashMap;
+
+type Coord = i64; // A coordinate on the map. May be negative.
+type Count = u32; // Number of lines crossing a point.
+
+// Grids with more cells than this are stored sparsely. A dense grid of
+// this many cells takes 64MB.
+const MAX_DENSE_CELLS: u64 = 1 << 24;
+
+// Storage for the number of lines crossing each point.
+enum Grid {
+    // One counter per point in the map's bounding box, row by row.
+    Dense(Vec<Count>),
+    // Counters for only the points that some line crosses.
+    Sparse(HashMap<(Coord, Coord), Count>),
+}
+
 struct VentMap {
+    x_min: Coord,
+    y_min: Coord,
     x_size: usize,
     y_size: usize,
-    grid: Vec<i8>,
+    grid: Grid,
+    lines: Vec<(Line, SlopeMode)>, // Every line set on the map
 }
 
 impl VentMap {
-    fn new(x_size: usize, y_size: usize) -> Self {
+    // Creates a map covering every point from (x_min, y_min) to
+    // (x_max, y_max), inclusive. The dense backend is used unless the
+    // grid would be too large.
+    fn new(x_min: Coord, y_min: Coord, x_max: Coord, y_max: Coord) -> Self {
+        assert!(x_min <= x_max);
+        assert!(y_min <= y_max);
+        let x_size = (x_max - x_min + 1) as u64;
+        let y_size = (y_max - y_min + 1) as u64;
+        let grid = match x_size.checked_mul(y_size) {
+            Some(cells) if cells <= MAX_DENSE_CELLS => Grid::Dense(vec![0; cells as usize]),
+            _ => Grid::Sparse(HashMap::new()),
+        };
         VentMap {
-            x_size: x_size,
-            y_size: y_size,
-            grid: vec![0; x_size * y_size],
-        }
-    }
-
-    fn set(&mut self, x: usize, y: usize) {
-        assert!(x < self.x_size);
-        assert!(y < self.y_size);
-        self.grid[self.x_size * y + x] += 1;
-    }
-
-    fn set_line(&mut self, x1: usize, y1: usize, x2: usize, y2: usize) {
-        assert!(x1 < self.x_size);
-        assert!(y1 < self.y_size);
-        assert!(x2 < self.x_size);
-        assert!(y2 < self.y_size);
-        
-        if x1 == x2 {
-            let mut y = std::cmp::min(y1, y2);
-            let y_max = std::cmp::max(y1, y2);
-            while y <= y_max {
-                self.set(x1, y);
-                y += 1;
+            x_min,
+            y_min,
+            x_size: x_size as usize,
+            y_size: y_size as usize,
+            grid,
+            lines: Vec::new(),
+        }
+    }
+
+    // Creates a map just large enough to hold every line.
+    fn new_for_lines(lines: &[Line]) -> Self {
+        let xs = || lines.iter().flat_map(|l| [l.x1, l.x2]);
+        let ys = || lines.iter().flat_map(|l| [l.y1, l.y2]);
+        VentMap::new(
+            xs().min().unwrap_or(0),
+            ys().min().unwrap_or(0),
+            xs().max().unwrap_or(0),
+            ys().max().unwrap_or(0))
+    }
+
+    fn is_sparse(&self) -> bool {
+        matches!(self.grid, Grid::Sparse(_))
+    }
+
+    fn set(&mut self, x: Coord, y: Coord) {
+        let dx = (x - self.x_min) as usize;
+        let dy = (y - self.y_min) as usize;
+        assert!(x >= self.x_min && dx < self.x_size);
+        assert!(y >= self.y_min && dy < self.y_size);
+        match &mut self.grid {
+            Grid::Dense(grid) => grid[self.x_size * dy + dx] += 1,
+            Grid::Sparse(grid) => *grid.entry((x, y)).or_insert(0) += 1,
+        }
+    }
+
+    // Number of lines crossing the point.
+    fn get(&self, x: Coord, y: Coord) -> Count {
+        match &self.grid {
+            Grid::Dense(grid) => grid[self.x_size * (y - self.y_min) as usize + (x - self.x_min) as usize],
+            Grid::Sparse(grid) => grid.get(&(x, y)).copied().unwrap_or(0),
+        }
+    }
+
+    // Every point crossed by at least one line, with its count.
+    fn crossed_points(&self) -> Vec<((Coord, Coord), Count)> {
+        match &self.grid {
+            Grid::Dense(grid) => grid.iter().enumerate()
+                .filter(|(_, n)| **n > 0)
+                .map(|(i, n)| {
+                    let x = self.x_min + (i % self.x_size) as Coord;
+                    let y = self.y_min + (i / self.x_size) as Coord;
+                    ((x, y), *n)
+                })
+                .collect(),
+            Grid::Sparse(grid) => grid.iter().map(|(p, n)| (*p, *n)).collect(),
+        }
+    }
+
+    // The largest number of lines crossing any one point.
+    fn max_count(&self) -> Count {
+        match &self.grid {
+            Grid::Dense(grid) => grid.iter().copied().max().unwrap_or(0),
+            Grid::Sparse(grid) => grid.values().copied().max().unwrap_or(0),
+        }
+    }
+
+    // Marks every point of the line, and remembers the line so it can be
+    // found again by the queries.
+    fn set_line(&mut self, line: &Line, mode: SlopeMode) -> Result<(), SlopeError> {
+        line.for_each_point(mode, |x, y| self.set(x, y))?;
+        self.lines.push((*line, mode));
+        Ok(())
+    }
+
+    // Number of points crossed by at least `threshold` lines.
+    fn get_overlapped_count(&self, threshold: Count) -> usize {
+        match &self.grid {
+            Grid::Dense(grid) => grid.iter().filter(|n|{ **n >= threshold }).count(),
+            Grid::Sparse(grid) => grid.values().filter(|n|{ **n >= threshold }).count(),
+        }
+    }
+}
+
+// How to draw lines that are neither straight nor at 45°.
+#[derive(Clone, Copy, PartialEq)]
+enum SlopeMode {
+    Strict,    // Reject them
+    Bresenham, // Mark the points closest to the line
+    Lattice,   // Mark only the points exactly on the line
+}
+
+// A line that cannot be drawn in strict mode.
+#[derive(Debug)]
+struct SlopeError {
+    line: Line,
+}
+
+impl std::fmt::Display for SlopeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} is neither horizontal, vertical nor at 45°", self.line)
+    }
+}
+
+fn gcd(a: Coord, b: Coord) -> Coord {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+// A line of vents, from (x1, y1) to (x2, y2).
+#[derive(Clone, Copy, Debug)]
+struct Line {
+    x1: Coord,
+    y1: Coord,
+    x2: Coord,
+    y2: Coord,
+}
+
+impl std::fmt::Display for Line {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{},{} -> {},{}", self.x1, self.y1, self.x2, self.y2)
+    }
+}
+
+impl Line {
+    // Calls f with every point of the line. Horizontal, vertical and 45°
+    // lines cover every point between their ends. Other slopes are handled
+    // as the mode says; in strict mode they are an error.
+    fn for_each_point(&self, mode: SlopeMode, mut f: impl FnMut(Coord, Coord)) -> Result<(), SlopeError> {
+        let Line { x1, y1, x2, y2 } = *self;
+        let (dx, dy) = (x2 - x1, y2 - y1);
+        if dx == 0 || dy == 0 || dx.abs() == dy.abs() || mode == SlopeMode::Lattice {
+            // Step from one lattice point on the line to the next. For the
+            // straight and 45° lines, that is every point in between.
+            let steps = gcd(dx.abs(), dy.abs());
+            let (step_x, step_y) = if steps == 0 { (0, 0) } else { (dx / steps, dy / steps) };
+            for i in 0..=steps {
+                f(x1 + i * step_x, y1 + i * step_y);
             }
         }
-        else if y1 == y2 {
-            let mut x = std::cmp::min(x1, x2);
-            let x_max = std::cmp::max(x1, x2);
-            while x <= x_max {
-                self.set(x, y1);
-                x += 1;
+        else if mode == SlopeMode::Bresenham {
+            // Rasterize the line, one point per step along its longer axis.
+            let (sx, sy) = (dx.signum(), dy.signum());
+            let (dx, dy) = (dx.abs(), -dy.abs());
+            let (mut x, mut y) = (x1, y1);
+            let mut err = dx + dy;
+            loop {
+                f(x, y);
+                if x == x2 && y == y2 {
+                    break;
+                }
+                let err2 = 2 * err;
+                if err2 >= dy {
+                    err += dy;
+                    x += sx;
+                }
+                if err2 <= dx {
+                    err += dx;
+                    y += sy;
+                }
             }
         }
         else {
-            let p1: (usize, usize); // Point with lesser x value.
-            let p2: (usize, usize); // Point with greater x value.
-            if x1 < x2 {
-                p1 = (x1, y1);
-                p2 = (x2, y2);
-            } else {
-                p1 = (x2, y2);
-                p2 = (x1, y1);
-            }
-            let dy: i32 = if p1.1 < p2.1 { 1 } else { -1 }; // slope is +1 or -1
-            let (mut x, mut y) = p1;
-            while x <= p2.0 {
-                self.set(x, y);
-                x += 1;
-                y = (y as i32 + dy) as usize;
-            }
+            return Err(SlopeError { line: *self });
         }
+        Ok(())
+    }
+
+    // Whether the line is neither horizontal nor vertical.
+    fn is_diagonal(&self) -> bool {
+        self.x1 != self.x2 && self.y1 != self.y2
     }
 
-    fn get_overlapped_count(&self) -> usize {
-        self.grid.iter().filter(|n|{ *n > &1 }).count()
+    // Parses a line like "0,9 -> -5,9".
+    fn parse(s: &str) -> Self {
+        let (p1, p2) = s.split_once("->").unwrap_or_else(|| panic!("Line is missing '->': {}", s));
+        let (x1, y1) = parse_point(p1);
+        let (x2, y2) = parse_point(p2);
+        Line { x1, y1, x2, y2 }
     }
 }
 
-fn parse(s: &str) -> usize {
-    usize::from_str_radix(s, 10).expect("Failed to parse value")
+fn parse_point(s: &str) -> (Coord, Coord) {
+    let (x, y) = s.split_once(',').unwrap_or_else(|| panic!("Point is missing ',': {}", s));
+    (parse(x), parse(y))
+}
+
+fn parse(s: &str) -> Coord {
+    s.trim().parse().expect("Failed to parse value")
+}
+
+// A question about the map, asked on the command line.
+enum Query {
+    Rect((Coord, Coord), (Coord, Coord)),
+    Top(usize),
+    Through((Coord, Coord)),
+    Clusters,
+}
+
+// Parses a flag's value made of `count` comma-separated coordinates.
+fn parse_coords(flag: &str, value: Option<String>, count: usize) -> Vec<Coord> {
+    let value = value.unwrap_or_else(|| panic!("{} requires {} coordinates", flag, count));
+    let coords: Vec<Coord> = value.split(',').map(parse).collect();
+    assert_eq!(coords.len(), count, "{} requires {} coordinates: {}", flag, count, value);
+    coords
 }
 
+fn run_query(vm: &VentMap, query: &Query, threshold: Count) {
+    match query {
+        Query::Rect((x1, y1), (x2, y2)) => println!(
+            "{} points in ({},{})-({},{}) have at least {} overlapping lines",
+            vm.count_in_rect((*x1, *y1), (*x2, *y2), threshold), x1, y1, x2, y2, threshold),
+        Query::Top(k) => {
+            println!("Top {} most crossed points:", k);
+            for ((x, y), count) in vm.top_points(*k) {
+                println!("  ({},{}): {} lines", x, y, count);
+            }
+        }
+        Query::Through((x, y)) => {
+            let lines = vm.lines_through((*x, *y));
+            println!("{} lines cross ({},{}):", lines.len(), x, y);
+            for line in lines {
+                println!("  {}", line);
+            }
+        }
+        Query::Clusters => {
+            let clusters = vm.clusters(threshold);
+            println!("{} clusters of points with at least {} overlapping lines", clusters.len(), threshold);
+            for (id, cluster) in clusters.iter().enumerate() {
+                println!(
+                    "  Cluster {}: {} points in ({},{})-({},{}), up to {} lines",
+                    id, cluster.points.len(), cluster.min.0, cluster.min.1,
+                    cluster.max.0, cluster.max.1, cluster.max_count);
+            }
+        }
+    }
+}
+
+// Options:
+//   --min-overlaps N                   Count the points crossed by at least N lines, N >= 1
+//                                      (default: 2)
+//   --no-diagonals                     Ignore lines that aren't horizontal or vertical (part 1)
+//   --slopes strict|bresenham|lattice  How to draw lines that aren't at 45° (default: strict)
+//   --engine grid|analytic|both        Count overlaps by drawing the lines on a map, by
+//                                      intersecting them directly, or both, checking that
+//                                      they agree (default: grid)
+//   --heatmap FILE                     Write the overlap counts as a .pgm, .ppm or .svg image
+//   --overlay                          Draw the lines on top of the .svg heatmap
+//   --rect X1,Y1,X2,Y2                 Count the overlapped points inside a rectangle
+//   --top K                            List the K most crossed points
+//   --through X,Y                      List the lines crossing a point
+//   --clusters                         Group the overlapped points into touching clusters
 fn main() {
-    let mut vm = VentMap::new(1000, 1000);
+    let mut threshold: Count = 2;
+    let mut diagonals = true;
+    let mut mode = SlopeMode::Strict;
+    let mut engine = "grid";
+    let mut heatmap_path: Option<String> = None;
+    let mut overlay = false;
+    let mut queries: Vec<Query> = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--min-overlaps" => {
+                // Every point off the lines is crossed by 0 of them, so a
+                // threshold of 0 would count whatever the map happens to hold.
+                threshold = args.next()
+                    .and_then(|n| n.parse().ok())
+                    .filter(|&n| n > 0)
+                    .expect("--min-overlaps requires a number of at least 1");
+            }
+            "--no-diagonals" => diagonals = false,
+            "--slopes" => {
+                mode = match args.next().as_deref() {
+                    Some("strict") => SlopeMode::Strict,
+                    Some("bresenham") => SlopeMode::Bresenham,
+                    Some("lattice") => SlopeMode::Lattice,
+                    _ => panic!("--slopes requires strict, bresenham or lattice"),
+                };
+            }
+            "--engine" => {
+                engine = match args.next().as_deref() {
+                    Some("grid") => "grid",
+                    Some("analytic") => "analytic",
+                    Some("both") => "both",
+                    _ => panic!("--engine requires grid, analytic or both"),
+                };
+            }
+            "--heatmap" => heatmap_path = Some(args.next().expect("--heatmap requires a file name")),
+            "--overlay" => overlay = true,
+            "--rect" => {
+                let c = parse_coords(&arg, args.next(), 4);
+                queries.push(Query::Rect((c[0], c[1]), (c[2], c[3])));
+            }
+            "--top" => {
+                let k = args.next().and_then(|k| k.parse().ok()).expect("--top requires a number");
+                queries.push(Query::Top(k));
+            }
+            "--through" => {
+                let c = parse_coords(&arg, args.next(), 2);
+                queries.push(Query::Through((c[0], c[1])));
+            }
+            "--clusters" => queries.push(Query::Clusters),
+            _ => panic!("Unknown argument: {}", arg),
+        }
+    }
+
+    // Read every line first, so the map can be sized to fit them.
+    let mut lines: Vec<Line> = Vec::new();
     loop {
         let mut line = String::new();
         match std::io::stdin().read_line(&mut line) {
             Err(_) => panic!("Failed to read line"),
             Ok(0)  => break,
             Ok(_)  => {
-                let split: Vec<usize> = line
-                    .split(|ch|{ !char::is_numeric(ch) })
-                    .filter(|s|{ !s.is_empty() })
-                    .map(parse)
-                    .collect();
-                let x1 = split[0];
-                let y1 = split[1];
-                let x2 = split[2];
-                let y2 = split[3];
-                let slope = if x1 == x2 { f32::INFINITY } else { (y2 as f32 - y1 as f32) / (x2 as f32 - x1 as f32) };
-                println!("({},{}) -> ({},{})  slope: {}", x1, y1, x2, y2, slope);
-                vm.set_line(x1, y1, x2, y2)
+                if !line.trim().is_empty() {
+                    let line = Line::parse(&line);
+                    if diagonals || !line.is_diagonal() {
+                        lines.push(line);
+                    }
+                }
             }
         }
     }
-    println!("{} points have overlapping lines", vm.get_overlapped_count());
+
+    let mut grid_count: Option<usize> = None;
+    if engine != "analytic" {
+        let mut vm = VentMap::new_for_lines(&lines);
+        println!(
+            "Map spans ({},{}) to ({},{}), stored {}",
+            vm.x_min, vm.y_min,
+            vm.x_min + vm.x_size as Coord - 1, vm.y_min + vm.y_size as Coord - 1,
+            if vm.is_sparse() { "sparse" } else { "dense" });
+        for line in lines.iter() {
+            if let Err(e) = vm.set_line(line, mode) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        grid_count = Some(vm.get_overlapped_count(threshold));
+        if let Some(path) = &heatmap_path {
+            heatmap::write(&vm, &lines, path, overlay)
+                .unwrap_or_else(|e| panic!("Failed to write heatmap to {}: {}", path, e));
+            println!("Wrote heatmap to {}", path);
+        }
+        for query in queries.iter() {
+            run_query(&vm, query, threshold);
+        }
+    }
+    else if heatmap_path.is_some() || !queries.is_empty() {
+        panic!("--heatmap and queries require the grid engine");
+    }
+
+    // The analytic engine only finds points crossed by two or more lines,
+    // and can't reproduce Bresenham's rounding.
+    let mut analytic_count: Option<usize> = None;
+    if engine != "grid" {
+        assert_eq!(threshold, 2, "The analytic engine only supports --min-overlaps 2");
+        assert!(mode != SlopeMode::Bresenham, "The analytic engine does not support --slopes bresenham");
+        match intersect::count_overlaps(&lines, mode) {
+            Ok(count) => analytic_count = Some(count),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let (Some(grid), Some(analytic)) = (grid_count, analytic_count) {
+        assert_eq!(grid, analytic, "Grid and analytic engines disagree");
+    }
+    let count = grid_count.or(analytic_count).expect("No engine ran");
+    println!("{} points have at least {} overlapping lines", count, threshold);
 }
@@ -0,0 +1,103 @@
+use crate::{Coord, Count, Grid, Line, VentMap};
+use std::collections::{HashMap, HashSet};
+
+type Point = (Coord, Coord);
+
+// A group of dangerous points, each touching another one of the group
+// (diagonal neighbours included).
+pub struct Cluster {
+    pub points: Vec<Point>,
+    pub min: Point, // Corners of the cluster's bounding box
+    pub max: Point,
+    pub max_count: Count, // Most lines crossing any one of its points
+}
+
+impl VentMap {
+    // Number of points inside the rectangle from (x1, y1) to (x2, y2),
+    // inclusive, that are crossed by at least `threshold` lines.
+    pub fn count_in_rect(&self, (x1, y1): Point, (x2, y2): Point, threshold: Count) -> usize {
+        let (x_lo, x_hi) = (std::cmp::min(x1, x2), std::cmp::max(x1, x2));
+        let (y_lo, y_hi) = (std::cmp::min(y1, y2), std::cmp::max(y1, y2));
+        let inside = |x: Coord, y: Coord| x_lo <= x && x <= x_hi && y_lo <= y && y <= y_hi;
+        match &self.grid {
+            Grid::Dense(_) => {
+                // Only visit the part of the rectangle that is on the map.
+                let x_end = self.x_min + self.x_size as Coord - 1;
+                let y_end = self.y_min + self.y_size as Coord - 1;
+                let mut count = 0;
+                for y in std::cmp::max(y_lo, self.y_min)..=std::cmp::min(y_hi, y_end) {
+                    for x in std::cmp::max(x_lo, self.x_min)..=std::cmp::min(x_hi, x_end) {
+                        if self.get(x, y) >= threshold {
+                            count += 1;
+                        }
+                    }
+                }
+                count
+            }
+            Grid::Sparse(grid) => grid.iter()
+                .filter(|((x, y), n)| inside(*x, *y) && **n >= threshold)
+                .count(),
+        }
+    }
+
+    // The k points crossed by the most lines, most crossed first. Ties are
+    // broken by position, so the result doesn't depend on the backend.
+    pub fn top_points(&self, k: usize) -> Vec<(Point, Count)> {
+        let mut points = self.crossed_points();
+        points.sort_unstable_by(|(p1, n1), (p2, n2)| n2.cmp(n1).then(p1.cmp(p2)));
+        points.truncate(k);
+        points
+    }
+
+    // Every line that crosses the point, in the order they were set.
+    pub fn lines_through(&self, (x, y): Point) -> Vec<Line> {
+        self.lines.iter()
+            .filter(|(line, mode)| {
+                let mut found = false;
+                line.for_each_point(*mode, |px, py| found |= px == x && py == y)
+                    .expect("Line was already set on the map");
+                found
+            })
+            .map(|(line, _)| *line)
+            .collect()
+    }
+
+    // Groups the points crossed by at least `threshold` lines into
+    // clusters of touching points, largest cluster first.
+    pub fn clusters(&self, threshold: Count) -> Vec<Cluster> {
+        let dangerous: HashMap<Point, Count> = self.crossed_points().into_iter()
+            .filter(|(_, n)| *n >= threshold)
+            .collect();
+        let mut starts: Vec<Point> = dangerous.keys().copied().collect();
+        starts.sort_unstable();
+
+        let mut visited: HashSet<Point> = HashSet::new();
+        let mut clusters: Vec<Cluster> = Vec::new();
+        for start in starts {
+            if !visited.insert(start) {
+                continue;
+            }
+            // Flood fill from this point.
+            let mut cluster = Cluster { points: Vec::new(), min: start, max: start, max_count: 0 };
+            let mut stack: Vec<Point> = vec![start];
+            while let Some((x, y)) = stack.pop() {
+                cluster.points.push((x, y));
+                cluster.min = (std::cmp::min(cluster.min.0, x), std::cmp::min(cluster.min.1, y));
+                cluster.max = (std::cmp::max(cluster.max.0, x), std::cmp::max(cluster.max.1, y));
+                cluster.max_count = std::cmp::max(cluster.max_count, dangerous[&(x, y)]);
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        let next = (x + dx, y + dy);
+                        if dangerous.contains_key(&next) && visited.insert(next) {
+                            stack.push(next);
+                        }
+                    }
+                }
+            }
+            cluster.points.sort_unstable();
+            clusters.push(cluster);
+        }
+        clusters.sort_by(|a, b| b.points.len().cmp(&a.points.len()).then(a.min.cmp(&b.min)));
+        clusters
+    }
+}
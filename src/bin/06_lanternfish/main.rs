@@ -1,48 +1,374 @@
-struct FishCounter {
-    timers: [usize; 9] // at pos i = number of fish with timer = i.
+mod species;
+
+use advent_of_code_2021::BigUint;
+use std::io::Write;
+use std::iter::Sum;
+use std::ops::AddAssign;
+
+// The timer values that describe a species' lifecycle.
+#[derive(Clone)]
+struct Lifecycle {
+    buckets: usize, // Number of distinct timer values (0 to buckets - 1)
+    reset: usize,   // Timer of a fish right after it gives birth
+    newborn: usize, // Timer of a newborn fish
 }
 
-impl FishCounter {
+impl Lifecycle {
+    // Lanternfish: a fish gives birth every 7 days, and newborns take 2
+    // extra days for their first cycle.
     fn new() -> Self {
-        FishCounter { timers: [0; 9] }
+        Lifecycle { buckets: 9, reset: 6, newborn: 8 }
+    }
+
+    // Reads "key = value" lines from a config file. Keys are buckets,
+    // reset and newborn; any not given keep their current value. Empty
+    // lines and lines starting with '#' are ignored.
+    fn load(&mut self, path: &str) {
+        let contents = std::fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read config file {}: {}", path, e));
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=')
+                .unwrap_or_else(|| panic!("{}:{}: expected key = value", path, index + 1));
+            self.set(key.trim(), value.trim());
+        }
+    }
+
+    fn set(&mut self, key: &str, value: &str) {
+        let value: usize = value.parse()
+            .unwrap_or_else(|_| panic!("Invalid value for {}: {}", key, value));
+        match key {
+            "buckets" => self.buckets = value,
+            "reset" => self.reset = value,
+            "newborn" => self.newborn = value,
+            _ => panic!("Unknown lifecycle setting: {}", key),
+        }
+    }
+
+    // Adds buckets if needed, so both the reset and newborn timers fit.
+    fn fit_buckets(&mut self) {
+        self.buckets = std::cmp::max(self.buckets, std::cmp::max(self.reset, self.newborn) + 1);
+    }
+
+    fn validate(&self) {
+        assert!(
+            self.reset < self.buckets && self.newborn < self.buckets,
+            "reset ({}) and newborn ({}) timers must be less than the number of buckets ({})",
+            self.reset, self.newborn, self.buckets);
+    }
+}
+
+// A number of fish. BigUint counts exactly and never overflows; f64 allows
+// the fractional fish that rates (like mortality) produce.
+trait Population: Clone + Default + From<u32> + for<'a> AddAssign<&'a Self> + for<'a> Sum<&'a Self> {}
+
+impl<T> Population for T
+    where T: Clone + Default + From<u32> + for<'a> AddAssign<&'a T> + for<'a> Sum<&'a T> {}
+
+struct FishCounter<N: Population = BigUint> {
+    timers: Vec<N>, // at pos i = number of fish with timer = i.
+    lifecycle: Lifecycle,
+}
+
+impl<N: Population> FishCounter<N> {
+    fn new(lifecycle: Lifecycle) -> Self {
+        lifecycle.validate();
+        FishCounter { timers: vec![N::default(); lifecycle.buckets], lifecycle }
     }
 
     fn add_initial_fish(&mut self, timer: usize) {
-        self.timers[timer] += 1;
+        assert!(timer < self.timers.len(), "Initial timer {} is out of range", timer);
+        self.timers[timer] += &N::from(1);
     }
 
-    fn elapse_one_day(&mut self) -> usize {
-        self.timers.rotate_left(1);       // All fish decrease their timers by 1. Fish at 0 give birth!
-        self.timers[6] += self.timers[8]; // Fish that gave birth have 6 days remaining.
-        self.timers[8]                    // Return number of new fish!
+    fn elapse_one_day(&mut self) -> N {
+        let last = self.timers.len() - 1;
+        self.timers.rotate_left(1);                        // All fish decrease their timers by 1. Fish at 0 give birth!
+        let born = std::mem::take(&mut self.timers[last]); // The fish that were at 0 rotated to the end.
+        self.timers[self.lifecycle.reset] += &born;        // Fish that gave birth restart their cycle.
+        self.timers[self.lifecycle.newborn] += &born;      // Newborn fish start theirs.
+        born                                               // Return number of new fish!
     }
 
-    fn get_total(&self) -> usize {
+    fn get_total(&self) -> N {
         self.timers.iter().sum()
     }
 }
 
+impl FishCounter<BigUint> {
+    // The matrix M such that timers after one day = M * timers today. Entry
+    // [i][j] is the number of fish with timer i tomorrow for each fish with
+    // timer j today.
+    fn transition<T: Element>(&self, one: &T) -> Matrix<T> {
+        let n = self.timers.len();
+        let mut m: Matrix<T> = vec![vec![one.zero(); n]; n];
+        for i in 0..n - 1 {
+            m[i][i + 1] = one.clone(); // Timers decrease by 1
+        }
+        m[self.lifecycle.reset][0] = m[self.lifecycle.reset][0].add(one);     // Parent restarts its cycle
+        m[self.lifecycle.newborn][0] = m[self.lifecycle.newborn][0].add(one); // Newborn starts its own
+        m
+    }
+
+    // Total number of fish after the given number of days, computed by
+    // raising the transition matrix to that power. The counts themselves
+    // are not changed.
+    fn project<T: Element>(&self, days: u64, one: &T) -> T {
+        let m = matrix_power(&self.transition(one), days);
+        let mut total = one.zero();
+        for row in m.iter() {
+            for (factor, count) in row.iter().zip(self.timers.iter()) {
+                total = total.add(&factor.mul(&one.lift(count)));
+            }
+        }
+        total
+    }
+
+    // Exact total number of fish after the given number of days. The
+    // result has a number of digits proportional to the days, so this gets
+    // slow beyond about a million days (see MAX_EXACT_DAYS).
+    fn after_days(&self, days: u64) -> BigUint {
+        self.project(days, &BigUint::from(1u64))
+    }
+
+    // Total number of fish after the given number of days, modulo p. This
+    // stays fast and small for any number of days.
+    fn after_days_mod(&self, days: u64, p: u64) -> u64 {
+        assert!(p > 0, "Modulus must be positive");
+        self.project(days, &ModP { value: 1 % p, p }).value
+    }
+}
+
+// A value that the transition matrix can hold. Methods that make new
+// values take &self so that settings like the modulus carry over.
+trait Element: Clone {
+    fn zero(&self) -> Self;
+    fn lift(&self, n: &BigUint) -> Self;
+    fn add(&self, other: &Self) -> Self;
+    fn mul(&self, other: &Self) -> Self;
+}
+
+impl Element for BigUint {
+    fn zero(&self) -> Self {
+        BigUint::zero()
+    }
+
+    fn lift(&self, n: &BigUint) -> Self {
+        n.clone()
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        let mut sum = self.clone();
+        sum += other;
+        sum
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        self * other
+    }
+}
+
+// An integer modulo p.
+#[derive(Clone)]
+struct ModP {
+    value: u64,
+    p: u64,
+}
+
+impl Element for ModP {
+    fn zero(&self) -> Self {
+        ModP { value: 0, p: self.p }
+    }
+
+    fn lift(&self, n: &BigUint) -> Self {
+        ModP { value: n.rem_u64(self.p), p: self.p }
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        ModP { value: ((self.value as u128 + other.value as u128) % self.p as u128) as u64, p: self.p }
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        ModP { value: ((self.value as u128 * other.value as u128) % self.p as u128) as u64, p: self.p }
+    }
+}
+
+type Matrix<T> = Vec<Vec<T>>;
+
+fn matrix_multiply<T: Element>(a: &Matrix<T>, b: &Matrix<T>) -> Matrix<T> {
+    let n = a.len();
+    let zero = a[0][0].zero();
+    let mut c: Matrix<T> = vec![vec![zero; n]; n];
+    for i in 0..n {
+        for k in 0..n {
+            for j in 0..n {
+                c[i][j] = c[i][j].add(&a[i][k].mul(&b[k][j]));
+            }
+        }
+    }
+    c
+}
+
+// Raises a square matrix to a power by repeated squaring, so it takes
+// about log2(exp) multiplications.
+fn matrix_power<T: Element>(m: &Matrix<T>, mut exp: u64) -> Matrix<T> {
+    let n = m.len();
+    let zero = m[0][0].zero();
+    let one = zero.lift(&BigUint::from(1u64));
+    let mut result: Matrix<T> = vec![vec![zero; n]; n];
+    for (i, row) in result.iter_mut().enumerate() {
+        row[i] = one.clone();
+    }
+    let mut base = m.clone();
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = matrix_multiply(&result, &base);
+        }
+        exp >>= 1;
+        if exp > 0 {
+            base = matrix_multiply(&base, &base);
+        }
+    }
+    result
+}
+
+// Largest --after projected exactly. The population grows exponentially,
+// so its number of digits grows with the days, and multiplying such
+// numbers gets slow: this many days takes a few seconds.
+const MAX_EXACT_DAYS: u64 = 1_000_000;
+
+// Options:
+//   --config FILE   Read the lifecycle from FILE (see Lifecycle::load)
+//   --buckets N     Number of distinct timer values
+//   --reset N       Timer of a fish right after it gives birth
+//   --newborn N     Timer of a newborn fish
+//   --after N       Also project the total after N days. Exactly for N up to MAX_EXACT_DAYS;
+//                   larger N, e.g. 1000000000000, needs --modulo.
+//   --modulo P      Give that projection modulo P, instead of exactly
+//   --model FILE    Simulate the several species in FILE instead (see Model::load),
+//                   printing each one's total every day. Doesn't read stdin.
+//   --days N        Number of days to simulate (default: 256, or the last --report-at day)
+//   --report-at L   Only print the days in the comma-separated list L, e.g. 80,256
+//   --csv FILE      Write day, births, total and each bucket's count for every day to FILE
+// The defaults model lanternfish. Flags override the config file. If the
+// number of buckets isn't given on the command line, it grows as needed to
+// hold both timers.
+//
 // Day 80: 49056 new fish, 388739 in total
 // Day 256: 158720888232 new fish, 1741362314973 in total
 fn main() {
+    let mut lifecycle = Lifecycle::new();
+    let mut overrides: Vec<(String, String)> = Vec::new();
+    let mut after: Option<u64> = None;
+    let mut modulo: Option<u64> = None;
+    let mut days: Option<usize> = None;
+    let mut report_at: Option<Vec<usize>> = None;
+    let mut csv_path: Option<String> = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| panic!("{} requires a value", arg));
+        match arg.as_str() {
+            "--config" => lifecycle.load(&value),
+            "--model" => {
+                let mut model = species::Model::load(&value);
+                model.print_header();
+                model.print_day(0);
+                for day in 1..=model.days {
+                    model.elapse_one_day();
+                    model.print_day(day);
+                }
+                return;
+            }
+            "--after" => after = Some(value.parse().expect("--after requires a number of days")),
+            "--modulo" => modulo = Some(value.parse().expect("--modulo requires a number")),
+            "--days" => days = Some(value.parse().expect("--days requires a number")),
+            "--report-at" => report_at = Some(
+                value.split(',').map(|d| d.trim().parse().expect("--report-at requires a list of days")).collect()),
+            "--csv" => csv_path = Some(value),
+            "--buckets" | "--reset" | "--newborn" => overrides.push((arg[2..].to_string(), value)),
+            _ => panic!("Unknown argument: {}", arg),
+        }
+    }
+    if let (Some(days), None) = (after, modulo) {
+        assert!(days <= MAX_EXACT_DAYS,
+            "--after {} is too far to project exactly (at most {} days); pass --modulo P", days, MAX_EXACT_DAYS);
+    }
+    let buckets_given = overrides.iter().any(|(key, _)| key == "buckets");
+    for (key, value) in overrides.iter() {
+        lifecycle.set(key, value);
+    }
+    if !buckets_given {
+        lifecycle.fit_buckets();
+    }
+
     // Read input
     let mut line = String::new();
     std::io::stdin().read_line(&mut line).expect("Failed to read input");
     let input: Vec<usize> = line.trim()
         .split(',')
-        .map(|x|{ usize::from_str_radix(x, 10).expect("Failed to parse integer") })
+        .map(|x|{ x.parse().expect("Failed to parse integer") })
         .collect();
     
     // Set initial condition
-    let mut fc = FishCounter::new();
+    let mut fc: FishCounter = FishCounter::new(lifecycle);
     for i in input {
         fc.add_initial_fish(i);
     }
     println!("Initial condition: {} fish", fc.get_total());
 
+    // Project straight to the requested day.
+    if let Some(days) = after {
+        match modulo {
+            Some(p) => println!("After {} days: {} fish (mod {})", days, fc.after_days_mod(days, p), p),
+            None => println!("After {} days: {} fish", days, fc.after_days(days)),
+        }
+    }
+
     // Simulate N days
-    for day in 1..=256 {
+    let last_report = report_at.as_ref().and_then(|days| days.iter().copied().max());
+    if let (Some(days), Some(last_report)) = (days, last_report) {
+        assert!(last_report <= days, "--report-at day {} is after the last day simulated (--days {})", last_report, days);
+    }
+    let days = days.or(last_report).unwrap_or(256);
+    let mut csv = csv_path.as_ref().map(|path| {
+        let file = std::fs::File::create(path)
+            .unwrap_or_else(|e| panic!("Failed to create {}: {}", path, e));
+        std::io::BufWriter::new(file)
+    });
+    if let Some(out) = csv.as_mut() {
+        write_csv_header(out, fc.timers.len()).expect("Failed to write CSV");
+        write_csv_row(out, 0, &BigUint::zero(), &fc).expect("Failed to write CSV");
+    }
+    for day in 1..=days {
         let born = fc.elapse_one_day();
-        println!("Day {}: {} new fish, {} in total", day, born, fc.get_total());
+        if report_at.as_ref().is_none_or(|days| days.contains(&day)) {
+            println!("Day {}: {} new fish, {} in total", day, born, fc.get_total());
+        }
+        if let Some(out) = csv.as_mut() {
+            write_csv_row(out, day, &born, &fc).expect("Failed to write CSV");
+        }
+    }
+    if let (Some(path), Some(mut out)) = (csv_path, csv) {
+        out.flush().expect("Failed to write CSV");
+        println!("Wrote {} days to {}", days + 1, path);
+    }
+}
+
+fn write_csv_header(out: &mut impl Write, buckets: usize) -> std::io::Result<()> {
+    write!(out, "day,births,total")?;
+    for timer in 0..buckets {
+        write!(out, ",timer_{}", timer)?;
+    }
+    writeln!(out)
+}
+
+fn write_csv_row(out: &mut impl Write, day: usize, born: &BigUint, fc: &FishCounter) -> std::io::Result<()> {
+    write!(out, "{},{},{}", day, born, fc.get_total())?;
+    for count in fc.timers.iter() {
+        write!(out, ",{}", count)?;
     }
+    writeln!(out)
 }
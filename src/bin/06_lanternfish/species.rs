@@ -0,0 +1,224 @@
+use crate::{FishCounter, Lifecycle};
+
+// One species in the population model. Counts are fractional, since the
+// rates below remove or add parts of fish.
+struct Species {
+    name: String,
+    counter: FishCounter<f64>,
+    mortality: f64,        // Fraction of the population that dies each day
+    capacity: Option<f64>, // Population at which births stop entirely
+}
+
+// Each day, the predator eats `rate * predators * prey` of the prey, and
+// `efficiency` newborn predators are born for each prey eaten.
+struct Predation {
+    predator: usize,
+    prey: usize,
+    rate: f64,
+    efficiency: f64,
+}
+
+// Several species living together, as described by a model file.
+pub struct Model {
+    pub days: usize,
+    species: Vec<Species>,
+    predations: Vec<Predation>,
+}
+
+// Settings of a species, collected until its section of the file ends.
+struct SpeciesConfig {
+    name: String,
+    lifecycle: Lifecycle,
+    buckets_given: bool,
+    initial: Vec<usize>,
+    mortality: f64,
+    capacity: Option<f64>,
+}
+
+// Settings of a predation, collected until its section of the file ends.
+#[derive(Default)]
+struct PredationConfig {
+    predator: Option<String>,
+    prey: Option<String>,
+    rate: f64,
+    efficiency: f64,
+}
+
+enum Section {
+    Top,
+    Species(SpeciesConfig),
+    Predation(PredationConfig),
+}
+
+fn parse_value<T: std::str::FromStr>(key: &str, value: &str) -> T {
+    value.parse().unwrap_or_else(|_| panic!("Invalid value for {}: {}", key, value))
+}
+
+impl Model {
+    // Reads a model file. It uses the same "key = value" lines as a
+    // lifecycle config file, grouped into sections:
+    //
+    //     days = 100                # Top level: number of days to simulate
+    //
+    //     [species lanternfish]     # One section per species
+    //     reset = 6                 # Lifecycle settings, as in Lifecycle::load. The
+    //                               # buckets grow to fit unless they are given.
+    //     newborn = 8
+    //     initial = 3,4,3,1,2       # Timers of the initial fish
+    //     mortality = 0.01          # Optional, default 0
+    //     capacity = 1000000        # Optional, default unlimited
+    //
+    //     [predation]               # One section per predator/prey pair
+    //     predator = shark
+    //     prey = lanternfish
+    //     rate = 0.00001
+    //     efficiency = 0.1
+    pub fn load(path: &str) -> Self {
+        let contents = std::fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read model file {}: {}", path, e));
+        let mut model = Model { days: 256, species: Vec::new(), predations: Vec::new() };
+        let mut species_configs: Vec<SpeciesConfig> = Vec::new();
+        let mut predation_configs: Vec<PredationConfig> = Vec::new();
+        let mut section = Section::Top;
+        let finish = |section: Section, species: &mut Vec<SpeciesConfig>, predations: &mut Vec<PredationConfig>| {
+            match section {
+                Section::Top => {}
+                Section::Species(config) => species.push(config),
+                Section::Predation(config) => predations.push(config),
+            }
+        };
+
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[').and_then(|h| h.strip_suffix(']')) {
+                finish(section, &mut species_configs, &mut predation_configs);
+                section = match header.split_once(' ') {
+                    Some(("species", name)) => Section::Species(SpeciesConfig {
+                        name: name.trim().to_string(),
+                        lifecycle: Lifecycle::new(),
+                        buckets_given: false,
+                        initial: Vec::new(),
+                        mortality: 0.0,
+                        capacity: None,
+                    }),
+                    None if header == "predation" => Section::Predation(PredationConfig::default()),
+                    _ => panic!("{}:{}: unknown section [{}]", path, index + 1, header),
+                };
+                continue;
+            }
+            let (key, value) = line.split_once('=')
+                .unwrap_or_else(|| panic!("{}:{}: expected key = value", path, index + 1));
+            let (key, value) = (key.trim(), value.trim());
+            match &mut section {
+                Section::Top => match key {
+                    "days" => model.days = parse_value(key, value),
+                    _ => panic!("{}:{}: unknown setting {}", path, index + 1, key),
+                },
+                Section::Species(config) => match key {
+                    "initial" => config.initial = value.split(',').map(|t| parse_value(key, t.trim())).collect(),
+                    "mortality" => config.mortality = parse_value(key, value),
+                    "capacity" => config.capacity = Some(parse_value(key, value)),
+                    _ => {
+                        config.buckets_given |= key == "buckets";
+                        config.lifecycle.set(key, value);
+                    }
+                },
+                Section::Predation(config) => match key {
+                    "predator" => config.predator = Some(value.to_string()),
+                    "prey" => config.prey = Some(value.to_string()),
+                    "rate" => config.rate = parse_value(key, value),
+                    "efficiency" => config.efficiency = parse_value(key, value),
+                    _ => panic!("{}:{}: unknown setting {}", path, index + 1, key),
+                },
+            }
+        }
+        finish(section, &mut species_configs, &mut predation_configs);
+
+        for mut config in species_configs {
+            if !config.buckets_given {
+                config.lifecycle.fit_buckets();
+            }
+            let mut counter: FishCounter<f64> = FishCounter::new(config.lifecycle);
+            for timer in config.initial {
+                counter.add_initial_fish(timer);
+            }
+            model.species.push(Species {
+                name: config.name,
+                counter,
+                mortality: config.mortality,
+                capacity: config.capacity,
+            });
+        }
+        assert!(!model.species.is_empty(), "Model file {} has no [species] sections", path);
+
+        let find = |name: &Option<String>, role: &str| -> usize {
+            let name = name.as_ref().unwrap_or_else(|| panic!("A [predation] section has no {}", role));
+            model.species.iter().position(|s| &s.name == name)
+                .unwrap_or_else(|| panic!("Unknown {} species: {}", role, name))
+        };
+        let predations: Vec<Predation> = predation_configs.iter()
+            .map(|config| Predation {
+                predator: find(&config.predator, "predator"),
+                prey: find(&config.prey, "prey"),
+                rate: config.rate,
+                efficiency: config.efficiency,
+            })
+            .collect();
+        model.predations = predations;
+        model
+    }
+
+    // Advances every species by one day. Each species first ages and gives
+    // birth as in FishCounter::elapse_one_day, with births slowed as the
+    // population nears its capacity. Then some die, and the predators eat.
+    pub fn elapse_one_day(&mut self) {
+        for species in self.species.iter_mut() {
+            let total = species.counter.get_total();
+            let born = species.counter.elapse_one_day();
+            if let Some(capacity) = species.capacity {
+                let unborn = born * (total / capacity).clamp(0.0, 1.0);
+                species.counter.timers[species.counter.lifecycle.newborn] -= unborn;
+            }
+            for count in species.counter.timers.iter_mut() {
+                *count *= 1.0 - species.mortality;
+            }
+        }
+
+        // Work out every predation from today's totals before applying any.
+        let totals: Vec<f64> = self.species.iter().map(|s| s.counter.get_total()).collect();
+        let mut eaten = vec![0.0; self.species.len()];
+        let mut fed = vec![0.0; self.species.len()];
+        for predation in self.predations.iter() {
+            let meals = predation.rate * totals[predation.predator] * totals[predation.prey];
+            eaten[predation.prey] += meals;
+            fed[predation.predator] += predation.efficiency * meals;
+        }
+        for (i, species) in self.species.iter_mut().enumerate() {
+            // Prey are eaten from every timer bucket alike.
+            if totals[i] > 0.0 {
+                let survivors = (1.0 - eaten[i] / totals[i]).max(0.0);
+                for count in species.counter.timers.iter_mut() {
+                    *count *= survivors;
+                }
+            }
+            species.counter.timers[species.counter.lifecycle.newborn] += fed[i];
+        }
+    }
+
+    // Prints the header of the time series: one column per species.
+    pub fn print_header(&self) {
+        let names: Vec<&str> = self.species.iter().map(|s| s.name.as_str()).collect();
+        println!("day,{}", names.join(","));
+    }
+
+    // Prints one row of the time series: each species' total on that day.
+    pub fn print_day(&self, day: usize) {
+        let totals: Vec<String> = self.species.iter()
+            .map(|s| format!("{:.2}", s.counter.get_total()))
+            .collect();
+        println!("{},{}", day, totals.join(","));
+    }
+}
@@ -1,32 +1,353 @@
-fn fuel(crabs: &Vec<i32>, pos: i32) -> i32 {
-    crabs.iter().map(|c|{
+mod plane;
+mod solver;
 
-         let distance = (c - pos).abs();
-         (distance * (distance + 1)) / 2
+use plane::Distance;
+use solver::Crabs;
+use std::io::Write;
 
-     }).sum()
+type Pos = i64;  // A crab's horizontal position
+type Fuel = i128; // Fuel used. Wide enough for millions of crabs far apart.
+
+// How the solver looks for the best position under a cost function.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Search {
+    Median,     // The median crab is optimal (linear cost)
+    NearMean,   // The optimum is within a step or two of the mean
+    Ternary,    // Total fuel is convex in the position, so narrow it down
+    Exhaustive, // No shortcut; try every position
+}
+
+// How much fuel a crab burns to move a given distance.
+trait CostFunction {
+    fn cost(&self, distance: Pos) -> Fuel;
+    fn name(&self) -> String;
+
+    // Same as cost, for a distance that isn't a whole number of steps
+    // (crabs moving diagonally across a plane).
+    fn cost_f64(&self, distance: f64) -> f64;
+
+    fn search(&self) -> Search {
+        Search::Exhaustive
+    }
+
+    // Coefficients (a, b, div) such that cost(d) = (a*d + b*d*d) / div, if
+    // the cost has that form. Then total fuel can be computed from prefix
+    // sums of the positions and their squares, without visiting every crab.
+    fn polynomial(&self) -> Option<(Fuel, Fuel, Fuel)> {
+        None
+    }
+}
+
+// Each step costs 1 (part 1).
+struct Linear;
+
+impl CostFunction for Linear {
+    fn cost(&self, distance: Pos) -> Fuel {
+        distance as Fuel
+    }
+
+    fn cost_f64(&self, distance: f64) -> f64 {
+        distance
+    }
+
+    fn name(&self) -> String {
+        String::from("linear")
+    }
+
+    fn search(&self) -> Search {
+        Search::Median
+    }
+
+    fn polynomial(&self) -> Option<(Fuel, Fuel, Fuel)> {
+        Some((1, 0, 1))
+    }
 }
 
+// Each step costs 1 more than the last (part 2).
+struct Triangular;
+
+impl CostFunction for Triangular {
+    fn cost(&self, distance: Pos) -> Fuel {
+        let distance = distance as Fuel;
+        checked(distance.checked_mul(distance + 1)) / 2
+    }
+
+    fn cost_f64(&self, distance: f64) -> f64 {
+        distance * (distance + 1.0) / 2.0
+    }
+
+    fn name(&self) -> String {
+        String::from("triangular")
+    }
+
+    fn search(&self) -> Search {
+        Search::NearMean
+    }
+
+    fn polynomial(&self) -> Option<(Fuel, Fuel, Fuel)> {
+        Some((1, 1, 2))
+    }
+}
+
+// Moving d steps costs d squared.
+struct Quadratic;
+
+impl CostFunction for Quadratic {
+    fn cost(&self, distance: Pos) -> Fuel {
+        let distance = distance as Fuel;
+        checked(distance.checked_mul(distance))
+    }
+
+    fn cost_f64(&self, distance: f64) -> f64 {
+        distance * distance
+    }
+
+    fn name(&self) -> String {
+        String::from("quadratic")
+    }
+
+    fn search(&self) -> Search {
+        Search::NearMean
+    }
+
+    fn polynomial(&self) -> Option<(Fuel, Fuel, Fuel)> {
+        Some((0, 1, 1))
+    }
+}
+
+// Moving d steps costs d to the given power.
+struct Power(u32);
+
+impl CostFunction for Power {
+    fn cost(&self, distance: Pos) -> Fuel {
+        checked((distance as Fuel).checked_pow(self.0))
+    }
+
+    fn cost_f64(&self, distance: f64) -> f64 {
+        distance.powi(self.0 as i32)
+    }
+
+    fn name(&self) -> String {
+        format!("power:{}", self.0)
+    }
+
+    fn search(&self) -> Search {
+        match self.0 {
+            1 => Search::Median,
+            2 => Search::NearMean,
+            _ => Search::Ternary,
+        }
+    }
+
+    fn polynomial(&self) -> Option<(Fuel, Fuel, Fuel)> {
+        match self.0 {
+            1 => Some((1, 0, 1)),
+            2 => Some((0, 1, 1)),
+            _ => None,
+        }
+    }
+}
+
+// Each step costs a rate that depends on how far the crab has already
+// moved. rates[i] = (d, r) means steps beyond distance d cost r each, until
+// the next entry takes over. The first entry starts at distance 0.
+struct Piecewise {
+    rates: Vec<(Pos, Fuel)>,
+}
+
+impl CostFunction for Piecewise {
+    fn cost(&self, distance: Pos) -> Fuel {
+        let mut total: Fuel = 0;
+        for (i, &(start, rate)) in self.rates.iter().enumerate() {
+            if distance <= start {
+                break;
+            }
+            let end = self.rates.get(i + 1).map_or(distance, |&(next, _)| std::cmp::min(next, distance));
+            total = checked(((end - start) as Fuel).checked_mul(rate).and_then(|f| total.checked_add(f)));
+        }
+        total
+    }
+
+    fn cost_f64(&self, distance: f64) -> f64 {
+        let mut total = 0.0;
+        for (i, &(start, rate)) in self.rates.iter().enumerate() {
+            if distance <= start as f64 {
+                break;
+            }
+            let end = self.rates.get(i + 1).map_or(distance, |&(next, _)| (next as f64).min(distance));
+            total += (end - start as f64) * rate as f64;
+        }
+        total
+    }
+
+    fn name(&self) -> String {
+        let rates: Vec<String> = self.rates.iter().map(|(d, r)| format!("{}:{}", d, r)).collect();
+        format!("piecewise:{}", rates.join(","))
+    }
+
+    // Rates that never drop below zero and never decrease make each crab's
+    // cost convex in the target position, and so is the total.
+    fn search(&self) -> Search {
+        let convex = self.rates[0].1 >= 0 && self.rates.windows(2).all(|w| w[0].1 <= w[1].1);
+        if convex { Search::Ternary } else { Search::Exhaustive }
+    }
+}
+
+// Parses a cost function from the command line: linear, triangular,
+// quadratic, power:P, or piecewise:R0,D1:R1,D2:R2,... (rate R0 per step
+// from distance 0, rate R1 per step beyond distance D1, and so on).
+fn parse_cost(s: &str) -> Box<dyn CostFunction> {
+    let (kind, params) = s.split_once(':').unwrap_or((s, ""));
+    match kind {
+        "linear" => Box::new(Linear),
+        "triangular" => Box::new(Triangular),
+        "quadratic" => Box::new(Quadratic),
+        "power" => Box::new(Power(params.parse().unwrap_or_else(|_| panic!("Invalid power: {}", s)))),
+        "piecewise" => {
+            let mut rates: Vec<(Pos, Fuel)> = Vec::new();
+            for (i, part) in params.split(',').enumerate() {
+                let (start, rate) = match part.split_once(':') {
+                    Some((start, rate)) => (start, rate),
+                    None if i == 0 => ("0", part),
+                    None => panic!("Invalid piecewise cost: {}", s),
+                };
+                let start: Pos = start.parse().unwrap_or_else(|_| panic!("Invalid piecewise cost: {}", s));
+                let rate: Fuel = rate.parse().unwrap_or_else(|_| panic!("Invalid piecewise cost: {}", s));
+                assert!(rates.last().is_none_or(|&(prev, _)| prev < start), "Piecewise distances must increase: {}", s);
+                rates.push((start, rate));
+            }
+            assert_eq!(rates[0].0, 0, "Piecewise cost must start at distance 0: {}", s);
+            Box::new(Piecewise { rates })
+        }
+        _ => panic!("Unknown cost function: {}", s),
+    }
+}
+
+// Parses comma-separated positions, e.g. "16,1,2,0". An empty string
+// gives no positions.
+fn parse_positions(s: &str) -> Vec<Pos> {
+    s.trim().split(',')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.trim().parse().unwrap_or_else(|_| panic!("Invalid crab position: {}", s)))
+        .collect()
+}
+
+// Stops the program if a fuel computation overflowed. Fuel is an i128, so
+// this only happens with huge distances or steep cost functions.
+fn checked(fuel: Option<Fuel>) -> Fuel {
+    fuel.unwrap_or_else(|| {
+        eprintln!("Fuel overflows i128");
+        std::process::exit(1);
+    })
+}
+
+fn fuel(crabs: &[Pos], pos: Pos, cost: &dyn CostFunction) -> Fuel {
+    checked(crabs.iter().try_fold(0 as Fuel, |total, c| total.checked_add(cost.cost((c - pos).abs()))))
+}
+
+// Writes the total fuel for every position between the outermost crabs,
+// one column per cost function.
+fn write_curve(path: &str, crabs: &Crabs, costs: &[(String, Box<dyn CostFunction>)]) -> std::io::Result<()> {
+    let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let names: Vec<String> = costs.iter().map(|(_, cost)| cost.name()).collect();
+    writeln!(out, "position,{}", names.join(","))?;
+    if let Some((lo, hi)) = crabs.range() {
+        for pos in lo..=hi {
+            write!(out, "{}", pos)?;
+            for (_, cost) in costs.iter() {
+                write!(out, ",{}", crabs.fuel(pos, cost.as_ref()))?;
+            }
+            writeln!(out)?;
+        }
+    }
+    out.flush()
+}
+
+// Options:
+//   --cost COST        Use COST (see parse_cost) instead of the two puzzle parts. May be
+//                      given more than once.
+//   --search MODE      How to find the best position: fast (the shortcut for each cost
+//                      function), exhaustive (try every position between the outermost
+//                      crabs) or both (checking that they agree). Default: fast.
+//   --per-crab         Also print each crab's fuel to reach the (leftmost) best position
+//   --curve FILE       Write the total fuel at every position, for each cost, to FILE
+//   --plane DISTANCE   Align crabs on a plane instead, measuring how far they move by
+//                      manhattan or euclidean distance (see plane::run)
+//
+// Positions may be negative. The best positions are only looked for between
+// the outermost crabs, and tied positions are all reported.
 fn main() {
+    let mut costs: Vec<(String, Box<dyn CostFunction>)> = Vec::new();
+    let mut search = "fast";
+    let mut per_crab = false;
+    let mut curve_path: Option<String> = None;
+    let mut plane: Option<Distance> = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--cost" => {
+                let cost = parse_cost(&args.next().expect("--cost requires a cost function"));
+                costs.push((cost.name(), cost));
+            }
+            "--search" => {
+                search = match args.next().as_deref() {
+                    Some("fast") => "fast",
+                    Some("exhaustive") => "exhaustive",
+                    Some("both") => "both",
+                    _ => panic!("--search requires fast, exhaustive or both"),
+                }
+            }
+            "--per-crab" => per_crab = true,
+            "--curve" => curve_path = Some(args.next().expect("--curve requires a file")),
+            "--plane" => {
+                plane = match args.next().as_deref() {
+                    Some("manhattan") => Some(Distance::Manhattan),
+                    Some("euclidean") => Some(Distance::Euclidean),
+                    _ => panic!("--plane requires manhattan or euclidean"),
+                }
+            }
+            _ => panic!("Unknown argument: {}", arg),
+        }
+    }
+    if costs.is_empty() {
+        costs.push((String::from("Part 1"), Box::new(Linear)));
+        costs.push((String::from("Part 2"), Box::new(Triangular)));
+    }
+    if let Some(distance) = plane {
+        assert!(curve_path.is_none(), "--curve is not supported with --plane");
+        plane::run(distance, &costs, search, per_crab);
+        return;
+    }
+
     // Read input
     let mut line = String::new();
-    std::io::stdin().read_line(&mut line).unwrap();
-    let crabs: Vec<i32> = line.trim().split(',').map(|s|{ i32::from_str_radix(s, 10).unwrap() }).collect();
+    std::io::stdin().read_line(&mut line).expect("Error reading from stdin");
+    let positions = parse_positions(&line);
+    if positions.is_empty() {
+        eprintln!("No crab positions in input");
+        std::process::exit(1);
+    }
+    let crabs = Crabs::new(&positions);
 
-    // Compute minimal fuel usage by trying all points between 0 and the max position.
-    let max_pos = crabs.iter().max().unwrap();
-    let mut min_fuel: Option<i32> = None;
-    for i in 0..=*max_pos {
-        let cur_fuel = fuel(&crabs, i);
-        if let Some(cur_min) = min_fuel {
-            if cur_fuel < cur_min {
-                min_fuel = Some(cur_fuel);
-            }
+    for (label, cost) in costs.iter() {
+        let cost = cost.as_ref();
+        let fast = if search != "exhaustive" { crabs.optimum(cost) } else { None };
+        let exhaustive = if search != "fast" { crabs.optimum_exhaustive(cost) } else { None };
+        if let (Some(fast), Some(exhaustive)) = (&fast, &exhaustive) {
+            assert_eq!(fast, exhaustive, "Fast and exhaustive searches disagree for {}", cost.name());
         }
-        else {
-            min_fuel = Some(cur_fuel);
+        let optimum = fast.or(exhaustive).expect("There are crabs");
+        println!("{}: {}", label, optimum);
+        if per_crab {
+            let target = optimum.first();
+            for (i, &pos) in positions.iter().enumerate() {
+                println!("  crab {} at {}: {} fuel", i, pos, cost.cost((pos - target).abs()));
+            }
         }
     }
 
-    println!("Min fuel = {}", min_fuel.unwrap());
+    if let Some(path) = curve_path {
+        write_curve(&path, &crabs, &costs).unwrap_or_else(|e| panic!("Failed to write {}: {}", path, e));
+        println!("Wrote fuel curve to {}", path);
+    }
 }
@@ -0,0 +1,154 @@
+use crate::solver::{ternary_search, Crabs};
+use crate::{checked, parse_positions, CostFunction, Fuel, Pos, Search};
+
+type Point = (Pos, Pos);
+
+// How far a crab moves to reach the meeting point.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Distance {
+    Manhattan, // Along the axes, a whole number of steps
+    Euclidean, // In a straight line, to a meeting point anywhere on the plane
+}
+
+// Reads crabs from stdin as whitespace-separated x,y pairs, e.g.
+// "16,1 2,0 4,-2", across any number of lines.
+fn read_crabs() -> Vec<Point> {
+    let mut crabs: Vec<Point> = Vec::new();
+    while let Some(line) = advent_of_code_2021::stdin_read_line() {
+        for pair in line.split_whitespace() {
+            match parse_positions(pair)[..] {
+                [x, y] => crabs.push((x, y)),
+                _ => panic!("Expected an x,y pair, got: {}", pair),
+            }
+        }
+    }
+    crabs
+}
+
+fn manhattan_fuel(crabs: &[Point], (x, y): Point, cost: &dyn CostFunction) -> Fuel {
+    checked(crabs.iter().try_fold(0 as Fuel, |total, (cx, cy)| {
+        total.checked_add(cost.cost((cx - x).abs() + (cy - y).abs()))
+    }))
+}
+
+fn euclidean_fuel(crabs: &[Point], (x, y): (f64, f64), cost: &dyn CostFunction) -> f64 {
+    crabs.iter().map(|&(cx, cy)| cost.cost_f64((cx as f64 - x).hypot(cy as f64 - y))).sum()
+}
+
+// Corners of the smallest rectangle holding every crab.
+fn bounds(crabs: &[Point]) -> (Point, Point) {
+    let xs = crabs.iter().map(|c| c.0);
+    let ys = crabs.iter().map(|c| c.1);
+    ((xs.clone().min().unwrap(), ys.clone().min().unwrap()), (xs.max().unwrap(), ys.max().unwrap()))
+}
+
+// The meeting point with the least total fuel, moving along the axes. With
+// linear cost the axes are independent, so each coordinate is the median
+// one. With other convex costs the total is convex in each coordinate, so
+// search x for the best of the best y at each x.
+fn manhattan_optimum(crabs: &[Point], cost: &dyn CostFunction) -> (Point, Fuel) {
+    let ((x_lo, y_lo), (x_hi, y_hi)) = bounds(crabs);
+    let point = match cost.search() {
+        Search::Median => {
+            let median = |coords: Vec<Pos>| Crabs::new(&coords).optimum(cost).unwrap().first();
+            (median(crabs.iter().map(|c| c.0).collect()), median(crabs.iter().map(|c| c.1).collect()))
+        }
+        Search::NearMean | Search::Ternary => {
+            let best_y = |x: Pos| ternary_search(y_lo, y_hi, |y| manhattan_fuel(crabs, (x, y), cost));
+            let x = ternary_search(x_lo, x_hi, |x| manhattan_fuel(crabs, (x, best_y(x)), cost));
+            (x, best_y(x))
+        }
+        Search::Exhaustive => return manhattan_optimum_exhaustive(crabs, cost),
+    };
+    (point, manhattan_fuel(crabs, point, cost))
+}
+
+// Same as manhattan_optimum, but trying every point of the rectangle
+// holding the crabs.
+fn manhattan_optimum_exhaustive(crabs: &[Point], cost: &dyn CostFunction) -> (Point, Fuel) {
+    let ((x_lo, y_lo), (x_hi, y_hi)) = bounds(crabs);
+    let mut best = ((x_lo, y_lo), manhattan_fuel(crabs, (x_lo, y_lo), cost));
+    for y in y_lo..=y_hi {
+        for x in x_lo..=x_hi {
+            let fuel = manhattan_fuel(crabs, (x, y), cost);
+            if fuel < best.1 {
+                best = ((x, y), fuel);
+            }
+        }
+    }
+    best
+}
+
+// The real x between lo and hi where the convex function f is smallest,
+// to within 1e-6.
+fn ternary_search_f64(mut lo: f64, mut hi: f64, f: impl Fn(f64) -> f64) -> f64 {
+    while hi - lo > 1e-6 {
+        let m1 = lo + (hi - lo) / 3.0;
+        let m2 = hi - (hi - lo) / 3.0;
+        if f(m1) < f(m2) {
+            hi = m2;
+        } else {
+            lo = m1;
+        }
+    }
+    (lo + hi) / 2.0
+}
+
+// The meeting point with the least total fuel, moving in straight lines.
+// The total is convex for convex costs, so search x for the best of the
+// best y at each x, as on the axes.
+fn euclidean_optimum(crabs: &[Point], cost: &dyn CostFunction) -> ((f64, f64), f64) {
+    if cost.search() == Search::Exhaustive {
+        eprintln!("Warning: {} is not convex, so the meeting point may not be the best", cost.name());
+    }
+    let ((x_lo, y_lo), (x_hi, y_hi)) = bounds(crabs);
+    let (x_lo, y_lo, x_hi, y_hi) = (x_lo as f64, y_lo as f64, x_hi as f64, y_hi as f64);
+    let best_y = |x: f64| ternary_search_f64(y_lo, y_hi, |y| euclidean_fuel(crabs, (x, y), cost));
+    let x = ternary_search_f64(x_lo, x_hi, |x| euclidean_fuel(crabs, (x, best_y(x)), cost));
+    let point = (x, best_y(x));
+    (point, euclidean_fuel(crabs, point, cost))
+}
+
+// Aligns crabs on a plane, read from stdin (see read_crabs), and prints the
+// best meeting point for each cost. The search works as on a line, except
+// that there is no exhaustive search for euclidean distance, and only one
+// meeting point is reported when several are tied.
+pub fn run(distance: Distance, costs: &[(String, Box<dyn CostFunction>)], search: &str, per_crab: bool) {
+    let crabs = read_crabs();
+    if crabs.is_empty() {
+        eprintln!("No crab positions in input");
+        std::process::exit(1);
+    }
+
+    for (label, cost) in costs.iter() {
+        let cost = cost.as_ref();
+        match distance {
+            Distance::Manhattan => {
+                let fast = if search != "exhaustive" { Some(manhattan_optimum(&crabs, cost)) } else { None };
+                let exhaustive = if search != "fast" { Some(manhattan_optimum_exhaustive(&crabs, cost)) } else { None };
+                if let (Some(fast), Some(exhaustive)) = (fast, exhaustive) {
+                    assert_eq!(fast.1, exhaustive.1, "Fast and exhaustive searches disagree for {}", cost.name());
+                }
+                let ((x, y), fuel) = fast.or(exhaustive).unwrap();
+                println!("{}: Min fuel = {} at ({}, {})", label, fuel, x, y);
+                if per_crab {
+                    for (i, &(cx, cy)) in crabs.iter().enumerate() {
+                        let fuel = cost.cost((cx - x).abs() + (cy - y).abs());
+                        println!("  crab {} at ({}, {}): {} fuel", i, cx, cy, fuel);
+                    }
+                }
+            }
+            Distance::Euclidean => {
+                assert!(search == "fast", "--search {} is not supported with euclidean distance", search);
+                let ((x, y), fuel) = euclidean_optimum(&crabs, cost);
+                println!("{}: Min fuel = {:.3} at ({:.3}, {:.3})", label, fuel, x, y);
+                if per_crab {
+                    for (i, &(cx, cy)) in crabs.iter().enumerate() {
+                        let fuel = cost.cost_f64((cx as f64 - x).hypot(cy as f64 - y));
+                        println!("  crab {} at ({}, {}): {:.3} fuel", i, cx, cy, fuel);
+                    }
+                }
+            }
+        }
+    }
+}
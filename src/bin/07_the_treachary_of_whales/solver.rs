@@ -0,0 +1,155 @@
+use crate::{checked, fuel, CostFunction, Fuel, Pos, Search};
+
+// The crabs sorted by position, with prefix sums of their positions and of
+// the squares of their positions. sums[i] covers the first i crabs.
+pub struct Crabs {
+    sorted: Vec<Pos>,
+    sums: Vec<Fuel>,
+    squares: Vec<Fuel>,
+}
+
+impl Crabs {
+    pub fn new(positions: &[Pos]) -> Self {
+        let mut sorted = positions.to_vec();
+        sorted.sort_unstable();
+        let mut sums: Vec<Fuel> = vec![0];
+        let mut squares: Vec<Fuel> = vec![0];
+        for &pos in sorted.iter() {
+            let pos = pos as Fuel;
+            sums.push(sums.last().unwrap() + pos);
+            squares.push(checked(pos.checked_mul(pos).and_then(|sq| squares.last().unwrap().checked_add(sq))));
+        }
+        Crabs { sorted, sums, squares }
+    }
+
+    // Total fuel for every crab to move to `pos`. Polynomial costs take
+    // O(log n) using the prefix sums; others visit every crab.
+    pub fn fuel(&self, pos: Pos, cost: &dyn CostFunction) -> Fuel {
+        let (a, b, div) = match cost.polynomial() {
+            Some(coefficients) => coefficients,
+            None => return fuel(&self.sorted, pos, cost),
+        };
+        let n = self.sorted.len();
+        let k = self.sorted.partition_point(|&c| c < pos); // Crabs left of pos
+        let p = pos as Fuel;
+        let (left, right) = (k as Fuel, (n - k) as Fuel);
+        let (sum_left, sum_right) = (self.sums[k], self.sums[n] - self.sums[k]);
+        let (sq_left, sq_right) = (self.squares[k], self.squares[n] - self.squares[k]);
+        let distances = (left * p - sum_left) + (sum_right - right * p);
+        if b == 0 {
+            return checked(a.checked_mul(distances)) / div;
+        }
+        // Σ(p - c)² = left·p² - 2p·Σc + Σc² on the left, and mirrored on the right.
+        let squared = || -> Option<Fuel> {
+            let p2 = p.checked_mul(p)?;
+            let left_sq = left.checked_mul(p2)?.checked_sub(p.checked_mul(2 * sum_left)?)?.checked_add(sq_left)?;
+            let right_sq = sq_right.checked_sub(p.checked_mul(2 * sum_right)?)?.checked_add(right.checked_mul(p2)?)?;
+            a.checked_mul(distances)?.checked_add(b.checked_mul(left_sq.checked_add(right_sq)?)?)
+        };
+        checked(squared()) / div
+    }
+
+    // Positions of the outermost crabs, or None if there are no crabs.
+    pub fn range(&self) -> Option<(Pos, Pos)> {
+        Some((*self.sorted.first()?, *self.sorted.last()?))
+    }
+
+    // Minimal total fuel and every position between the outermost crabs
+    // that achieves it, using the shortcut the cost function allows. None
+    // if there are no crabs.
+    pub fn optimum(&self, cost: &dyn CostFunction) -> Option<Optimum> {
+        let (lo, hi) = self.range()?;
+        let best = match cost.search() {
+            Search::Median => self.sorted[self.sorted.len() / 2],
+            Search::NearMean => {
+                // The real optimum is within half a step of the mean, so the
+                // best whole position is within a step or two of it.
+                let mean = self.sums[self.sorted.len()].div_euclid(self.sorted.len() as Fuel) as Pos;
+                (mean - 1..=mean + 2)
+                    .map(|pos| pos.clamp(lo, hi))
+                    .min_by_key(|&pos| self.fuel(pos, cost))
+                    .unwrap()
+            }
+            Search::Ternary => ternary_search(lo, hi, |pos| self.fuel(pos, cost)),
+            Search::Exhaustive => return self.optimum_exhaustive(cost),
+        };
+
+        // Total fuel is convex, so the tied positions form one run around
+        // the best one. Binary search for each end of it.
+        let fuel = self.fuel(best, cost);
+        let (mut first, mut first_hi) = (lo, best);
+        while first < first_hi {
+            let mid = first + (first_hi - first) / 2;
+            if self.fuel(mid, cost) == fuel { first_hi = mid } else { first = mid + 1 }
+        }
+        let (mut last_lo, mut last) = (best, hi);
+        while last_lo < last {
+            let mid = last - (last - last_lo) / 2;
+            if self.fuel(mid, cost) == fuel { last_lo = mid } else { last = mid - 1 }
+        }
+        Some(Optimum { fuel, positions: vec![(first, last)] })
+    }
+
+    // Same as optimum, but trying every position between the outermost crabs.
+    pub fn optimum_exhaustive(&self, cost: &dyn CostFunction) -> Option<Optimum> {
+        let (lo, hi) = self.range()?;
+        let mut optimum = Optimum { fuel: self.fuel(lo, cost), positions: vec![(lo, lo)] };
+        for pos in lo + 1..=hi {
+            let fuel = self.fuel(pos, cost);
+            if fuel < optimum.fuel {
+                optimum = Optimum { fuel, positions: vec![(pos, pos)] };
+            } else if fuel == optimum.fuel {
+                match optimum.positions.last_mut() {
+                    Some((_, last)) if *last == pos - 1 => *last = pos,
+                    _ => optimum.positions.push((pos, pos)),
+                }
+            }
+        }
+        Some(optimum)
+    }
+}
+
+// The minimal total fuel, and the positions where it is reached as runs of
+// consecutive positions (first, last), in order.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Optimum {
+    pub fuel: Fuel,
+    pub positions: Vec<(Pos, Pos)>,
+}
+
+impl Optimum {
+    // The leftmost optimal position.
+    pub fn first(&self) -> Pos {
+        self.positions[0].0
+    }
+}
+
+impl std::fmt::Display for Optimum {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let runs: Vec<String> = self.positions.iter()
+            .map(|&(first, last)| if first == last { first.to_string() } else { format!("{}..{}", first, last) })
+            .collect();
+        let plural = self.positions.len() > 1 || self.positions[0].0 != self.positions[0].1;
+        write!(f, "Min fuel = {} at position{} {}", self.fuel, if plural { "s" } else { "" }, runs.join(", "))
+    }
+}
+
+// The position between lo and hi (inclusive) where the convex function f is
+// smallest. Whichever third point has the larger value can't have the
+// minimum beyond it, and on a tie the minimum is between them.
+pub fn ternary_search(mut lo: Pos, mut hi: Pos, f: impl Fn(Pos) -> Fuel) -> Pos {
+    while hi - lo > 2 {
+        let m1 = lo + (hi - lo) / 3;
+        let m2 = hi - (hi - lo) / 3;
+        let (f1, f2) = (f(m1), f(m2));
+        if f1 < f2 {
+            hi = m2 - 1;
+        } else if f1 > f2 {
+            lo = m1 + 1;
+        } else {
+            lo = m1;
+            hi = m2;
+        }
+    }
+    (lo..=hi).min_by_key(|&pos| f(pos)).unwrap()
+}
@@ -0,0 +1,57 @@
+use crate::glyphs::{GlyphSet, PatternId};
+use crate::{normalized_value, Arrangement, Entry};
+
+// Works out an entry's arrangement from its signal patterns alone, without
+// the lookup table. Returns None if the patterns don't fit any arrangement.
+// `glyphs` must be the 7-segment digits.
+//
+// Patterns are handled as pattern ids, so "contains" and "minus" are
+// bitwise operations. 1, 4, 7 and 8 are the only digits with 2, 4, 3 and 7
+// segments. Among the 6-segment digits, 9 contains all of 4, 0 contains 1
+// but not 4, and 6 doesn't contain 1. Each segment then falls out of the
+// differences between digits, e.g. segment a is 7 minus 1.
+pub fn deduce(entry: &Entry, glyphs: &GlyphSet) -> Option<Arrangement> {
+    let patterns: Vec<PatternId> = entry.signal_patterns.iter().map(|s| glyphs.pattern_id(s)).collect();
+
+    // The only pattern with the given number of segments that passes the test.
+    let find = |segments: u32, test: &dyn Fn(PatternId) -> bool| -> Option<PatternId> {
+        let mut matches = patterns.iter().copied().filter(|&p| p.count_ones() == segments && test(p));
+        let found = matches.next()?;
+        match matches.next() {
+            Some(_) => None,
+            None => Some(found),
+        }
+    };
+    let one = find(2, &|_| true)?;
+    let four = find(4, &|_| true)?;
+    let seven = find(3, &|_| true)?;
+    let eight = find(7, &|_| true)?;
+    let nine = find(6, &|p| p & four == four)?;
+    let zero = find(6, &|p| p & one == one && p & four != four)?;
+    let six = find(6, &|p| p & one != one)?;
+
+    let a = seven & !one;
+    let c = eight & !six;
+    let f = one & !c;
+    let d = eight & !zero;
+    let e = eight & !nine;
+    let b = four & !one & !d;
+    let g = eight & !(a | b | c | d | e | f);
+
+    let mut wire_targets: Vec<char> = Vec::new();
+    for segment in [a, b, c, d, e, f, g] {
+        if segment.count_ones() != 1 {
+            return None;
+        }
+        wire_targets.push(glyphs.segments[segment.trailing_zeros() as usize]);
+    }
+
+    // The deduction only looked at some of the digits. Make sure the rest
+    // agree.
+    let arrangement = Arrangement::new(wire_targets, glyphs);
+    if arrangement.normalized_id == normalized_value(&entry.signal_patterns, glyphs) {
+        Some(arrangement)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,91 @@
+use itertools::Itertools;
+
+// A signal or glyph as a set of segments: bit i is set if segment i is lit.
+// The same signal in any order gives the same id, e.g. "abcg" and "gcab".
+pub type PatternId = u32;
+
+pub const MAX_SEGMENTS: usize = PatternId::BITS as usize;
+
+// The symbols a display can show, and which segments each one lights.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GlyphSet {
+    pub segments: Vec<char>, // Names of the segments, also used for the wires
+    pub symbols: Vec<String>,
+    pub patterns: Vec<PatternId>, // patterns[i] lights up symbols[i]
+}
+
+impl GlyphSet {
+    // The ten digits of the 7-segment display in the puzzle.
+    pub fn digits() -> Self {
+        let lit = ["abcefg", "cf", "acdeg", "acdfg", "bcdf", "abdfg", "abdefg", "acf", "abcdefg", "abcdfg"];
+        let mut glyphs = GlyphSet {
+            segments: "abcdefg".chars().collect(),
+            symbols: Vec::new(),
+            patterns: Vec::new(),
+        };
+        for (digit, segments) in lit.iter().enumerate() {
+            glyphs.symbols.push(digit.to_string());
+            glyphs.patterns.push(glyphs.pattern_id(segments));
+        }
+        glyphs
+    }
+
+    // Reads a glyph set from a file. The first setting names the segments,
+    // one character each, and the rest give the segments each symbol lights:
+    //
+    //     # A 7-segment display showing hexadecimal digits
+    //     segments = abcdefg
+    //     0 = abcefg
+    //     1 = cf
+    //     ...
+    //     F = abde
+    //
+    // Lines starting with # are comments.
+    pub fn load(path: &str) -> Self {
+        let contents = std::fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read glyph file {}: {}", path, e));
+        let mut glyphs = GlyphSet { segments: Vec::new(), symbols: Vec::new(), patterns: Vec::new() };
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=')
+                .unwrap_or_else(|| panic!("{}:{}: expected symbol = segments", path, index + 1));
+            let (key, value) = (key.trim(), value.trim());
+            if glyphs.segments.is_empty() {
+                assert_eq!(key, "segments", "{}:{}: the segments must be named first", path, index + 1);
+                glyphs.segments = value.chars().collect();
+                assert!(glyphs.segments.len() <= MAX_SEGMENTS,
+                    "{}:{}: at most {} segments are supported", path, index + 1, MAX_SEGMENTS);
+                assert!(glyphs.segments.iter().all_unique(), "{}:{}: segment names must differ", path, index + 1);
+                continue;
+            }
+            let pattern = glyphs.pattern_id(value);
+            assert!(!glyphs.patterns.contains(&pattern),
+                "{}:{}: symbol {} looks the same as another one", path, index + 1, key);
+            glyphs.symbols.push(key.to_string());
+            glyphs.patterns.push(pattern);
+        }
+        assert!(!glyphs.symbols.is_empty(), "Glyph file {} has no symbols", path);
+        glyphs
+    }
+
+    // Position of the named segment (or wire).
+    pub fn segment_index(&self, name: char) -> usize {
+        self.segments.iter().position(|&s| s == name)
+            .unwrap_or_else(|| panic!("Invalid segment in signal: {}", name))
+    }
+
+    // Transforms a signal (or a glyph's segments) into its pattern id.
+    pub fn pattern_id(&self, signal: &str) -> PatternId {
+        signal.chars().fold(0, |id, ch| id | 1 << self.segment_index(ch))
+    }
+
+    // The symbols that can be told apart by their number of segments alone,
+    // e.g. 1, 4, 7 and 8 for the digits.
+    pub fn obvious_sizes(&self) -> Vec<u32> {
+        let sizes: Vec<u32> = self.patterns.iter().map(|p| p.count_ones()).collect();
+        sizes.iter().copied().filter(|&n| sizes.iter().filter(|&&m| m == n).count() == 1).collect()
+    }
+}
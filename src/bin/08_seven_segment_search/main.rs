@@ -1,5 +1,11 @@
+mod deduce;
+mod glyphs;
+mod search;
+
+use glyphs::{GlyphSet, PatternId};
 use itertools::Itertools;
 use std::collections::BTreeMap;
+use std::time::Instant;
 
 // One entry of puzzle input
 #[derive(Debug)]
@@ -20,7 +26,7 @@ fn read_entry() -> Option<Entry> {
         outputs: Vec::new(),
     };
     let mut dst = &mut entry.signal_patterns;
-    for token in line.split_ascii_whitespace().into_iter() {
+    for token in line.split_ascii_whitespace() {
         if token == "|" {
             dst = &mut entry.outputs;
             continue;
@@ -34,225 +40,266 @@ fn read_entry() -> Option<Entry> {
 #[derive(Debug)]
 struct Arrangement {
     wire_targets: Vec<char>,
-    segment_patterns: Vec<String>,
-    normalized_id: u128
+    segment_patterns: Vec<PatternId>,
+    normalized_id: Vec<PatternId>,
 }
 
 impl Arrangement {
 
-    #[allow(non_upper_case_globals)]
-    fn new(wire_targets: Vec<char>) -> Self {
-        let mut arr = Arrangement {
-            wire_targets: wire_targets,
-            segment_patterns: Vec::new(),
-            normalized_id: 0u128,
-        };
-
+    fn new(wire_targets: Vec<char>, glyphs: &GlyphSet) -> Self {
         // Wire normally destined for segment a is at position 0
         // in the arrangement. Wire normally destined for segment b
         // is at position 1, etc.
-        const a: usize = 0;
-        const b: usize = 1;
-        const c: usize = 2;
-        const d: usize = 3;
-        const e: usize = 4;
-        const f: usize = 5;
-        const g: usize = 6;
-
-        // Push the segments that are lit for each numeral. E.g. segment_patterns[2]
-        // will hold the segments that light up when the wires intend to signal
-        // the numeral 2.
-        arr.segment_patterns.push([ // 0
-            arr.wire_targets[a],
-            arr.wire_targets[b],
-            arr.wire_targets[c],
-            arr.wire_targets[e],
-            arr.wire_targets[f],
-            arr.wire_targets[g],
-        ].iter().collect());
+        let wire_bits: Vec<PatternId> = wire_targets.iter()
+            .map(|&wire| 1 << glyphs.segment_index(wire))
+            .collect();
 
-        arr.segment_patterns.push([ // 1
-            arr.wire_targets[c],
-            arr.wire_targets[f],
-        ].iter().collect());
+        // Light up the wires of the segments for each symbol. E.g. segment_patterns[2]
+        // will hold the wires that light up when the display intends to show
+        // symbols[2] (the numeral 2, for the puzzle's digits).
+        let segment_patterns: Vec<PatternId> = glyphs.patterns.iter()
+            .map(|&pattern| {
+                wire_bits.iter().enumerate()
+                    .filter(|(segment, _)| pattern & (1 << segment) != 0)
+                    .fold(0, |id, (_, bit)| id | bit)
+            })
+            .collect();
 
-        arr.segment_patterns.push([ // 2
-            arr.wire_targets[a],
-            arr.wire_targets[c],
-            arr.wire_targets[d],
-            arr.wire_targets[e],
-            arr.wire_targets[g],
-        ].iter().collect());
+        let mut normalized_id = segment_patterns.clone();
+        normalized_id.sort_unstable();
+        Arrangement { wire_targets, segment_patterns, normalized_id }
+    }
 
-        arr.segment_patterns.push([ // 3
-            arr.wire_targets[a],
-            arr.wire_targets[c],
-            arr.wire_targets[d],
-            arr.wire_targets[f],
-            arr.wire_targets[g],
-        ].iter().collect());
-        
-        arr.segment_patterns.push([ // 4
-            arr.wire_targets[b],
-            arr.wire_targets[c],
-            arr.wire_targets[d],
-            arr.wire_targets[f],
-        ].iter().collect());
+    // Decodes a single signal into the index of the symbol being communicated.
+    fn decode_one(&self, glyphs: &GlyphSet, signal: &str) -> usize {
+        let id = glyphs.pattern_id(signal);
+        self.segment_patterns.iter().position(|&pattern| pattern == id)
+            .unwrap_or_else(|| panic!("Arrangement {:?} cannot decode {}", self.wire_targets, signal))
+    }
 
-        arr.segment_patterns.push([ // 5
-            arr.wire_targets[a],
-            arr.wire_targets[b],
-            arr.wire_targets[d],
-            arr.wire_targets[f],
-            arr.wire_targets[g],
-        ].iter().collect());
+    // Decodes a series of signals to build up the intended text, e.g. an
+    // N-digit value.
+    fn decode_many(&self, glyphs: &GlyphSet, signals: &[String]) -> String {
+        signals.iter()
+            .map(|signal| glyphs.symbols[self.decode_one(glyphs, signal)].as_str())
+            .collect()
+    }
+}
 
-        arr.segment_patterns.push([ // 6
-            arr.wire_targets[a],
-            arr.wire_targets[b],
-            arr.wire_targets[d],
-            arr.wire_targets[e],
-            arr.wire_targets[f],
-            arr.wire_targets[g],
-        ].iter().collect());
+// Transforms a set of signals into a unique value describing that
+// set of signals. Each signal in the list is first normalized to its
+// pattern id. Then, the ids are sorted, which lets us detect if different
+// sets of signals are actually just re-arrangements of the same canonical
+// set.
+fn normalized_value(signals: &[String], glyphs: &GlyphSet) -> Vec<PatternId> {
+    let mut ids: Vec<PatternId> = signals.iter().map(|s| glyphs.pattern_id(s)).collect();
+    ids.sort_unstable();
+    ids
+}
 
-        arr.segment_patterns.push([ // 7
-            arr.wire_targets[a],
-            arr.wire_targets[c],
-            arr.wire_targets[f],
-        ].iter().collect());
+// Largest display the lookup table is built for: 9! = 362880 arrangements.
+const MAX_LOOKUP_SEGMENTS: usize = 9;
 
-        arr.segment_patterns.push([ // 8
-            arr.wire_targets[a],
-            arr.wire_targets[b],
-            arr.wire_targets[c],
-            arr.wire_targets[d],
-            arr.wire_targets[e],
-            arr.wire_targets[f],
-            arr.wire_targets[g],
-        ].iter().collect());
+type LookupTable = BTreeMap<Vec<PatternId>, Arrangement>;
 
-        arr.segment_patterns.push([ // 9
-            arr.wire_targets[a],
-            arr.wire_targets[b],
-            arr.wire_targets[c],
-            arr.wire_targets[d],
-            arr.wire_targets[f],
-            arr.wire_targets[g],
-        ].iter().collect());
+// For every possible arrangement, calculates the resulting signal patterns.
+// They are stored in a map to quickly lookup the arrangement given the
+// unique normalized value.
+fn build_lookup_table(glyphs: &GlyphSet) -> LookupTable {
+    let mut arrangements: LookupTable = BTreeMap::new();
+    let n = glyphs.segments.len();
+    for perm in glyphs.segments.iter().copied().permutations(n) {
+        let arrangement = Arrangement::new(perm, glyphs);
+        arrangements.insert(arrangement.normalized_id.clone(), arrangement);
+    }
+    arrangements
+}
 
-        arr.normalized_id = normalized_value(&arr.segment_patterns);
+// Finds the entry's arrangement in the pre-computed table by its normalized value.
+fn lookup<'a>(arrangements: &'a LookupTable, entry: &Entry, glyphs: &GlyphSet) -> &'a Arrangement {
+    let entry_id = normalized_value(&entry.signal_patterns, glyphs);
+    arrangements.get(&entry_id).unwrap_or_else(|| {
+        panic!("Did not find any arrangement for entry {:?} ({:?})", entry.signal_patterns, entry_id)
+    })
+}
 
-        return arr;
-    }
+// Ways to find an entry's arrangement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Decoder {
+    Lookup, // In a table of every arrangement (displays of up to MAX_LOOKUP_SEGMENTS)
+    Deduce, // From the entry's own patterns (the puzzle's digits only, see deduce::deduce)
+    Search, // By backtracking over the wiring (any glyph set, see search::search)
+}
 
-    // Decodes a single signal into the numeric value being communicated.
-    fn decode_one(&self, signal: &str) -> i32 {
-        for (i, check_signal) in self.segment_patterns.iter().enumerate() {
-            if normalized_byte(signal) == normalized_byte(&check_signal) {
-                return i as i32;
-            }
+impl Decoder {
+    fn name(self) -> &'static str {
+        match self {
+            Decoder::Lookup => "lookup",
+            Decoder::Deduce => "deduce",
+            Decoder::Search => "search",
         }
-        panic!("Arrangement {:?} cannot decode {}", self.wire_targets, signal);
     }
 
-    // Decodes a series of signals to build up the intended N-digit value.
-    fn decode_many(&self, signals: &Vec<String>) -> i32 {
-        let mut res = 0;
-        for signal in signals {
-            res = (10 * res) + self.decode_one(signal);
+    // Panics if the decoder can't handle the glyph set.
+    fn check(self, glyphs: &GlyphSet) {
+        match self {
+            Decoder::Lookup => assert!(glyphs.segments.len() <= MAX_LOOKUP_SEGMENTS,
+                "The lookup decoder supports at most {} segments", MAX_LOOKUP_SEGMENTS),
+            Decoder::Deduce => assert!(*glyphs == GlyphSet::digits(),
+                "The deduce decoder only supports the 7-segment digits"),
+            Decoder::Search => {}
         }
-        return res;
     }
-}
 
-// Transforms a signal into a single byte describing that signal.
-// The output value is shared by all arrangements of the same signal.
-// For instance, "abcg", "gcab", "agbc", etc. are all just different
-// arrangements of the same signal, so they have the same normalized
-// byte value.
-fn normalized_byte(signal: &str) -> u8 {
-    let mut byte = 0u8;
-    for ch in signal.chars() {
-        byte = byte | match ch {
-            'a' => 1,
-            'b' => 2,
-            'c' => 4,
-            'd' => 8,
-            'e' => 16,
-            'f' => 32,
-            'g' => 64,
-            _ => panic!("Invalid segment in signal: {}", ch)
-        };
+    // Decodes the entry's outputs. `table` is needed by the lookup decoder.
+    fn decode(self, table: Option<&LookupTable>, glyphs: &GlyphSet, entry: &Entry) -> String {
+        match self {
+            Decoder::Lookup => lookup(table.expect("Lookup table was not built"), entry, glyphs)
+                .decode_many(glyphs, &entry.outputs),
+            Decoder::Deduce => deduce::deduce(entry, glyphs)
+                .unwrap_or_else(|| panic!("Could not deduce an arrangement for entry {:?}", entry.signal_patterns))
+                .decode_many(glyphs, &entry.outputs),
+            Decoder::Search => search::search(entry, glyphs)
+                .unwrap_or_else(|| panic!("Could not find an arrangement for entry {:?}", entry.signal_patterns))
+                .decode_many(glyphs, &entry.outputs),
+        }
     }
-    return byte;
 }
 
-// Transforms a set of signals into a unique value describing that
-// set of signals. Each signal in the list is first normalized. Then,
-// the normalized values are sorted and joined together into an 80-bit
-// binary value optimized for comparisions. This lets us detect if
-// different sets of signals are actually just re-arrangements of the
-// same canonical set.
-fn normalized_value(signals: &Vec<String>) -> u128 {
-    let mut normalized_bytes: Vec<u8> = signals.iter()
-        .map(|s| { s.as_str() })
-        .map(normalized_byte)
-        .collect();
-    normalized_bytes.sort();
+// Times each decoder decoding every entry, `passes` times each.
+fn bench(entries: &[Entry], glyphs: &GlyphSet, decoders: &[Decoder], passes: usize) {
+    println!("Decoding {} entries {} times:", entries.len(), passes);
+    let mut lookup_time = None;
+    let mut first_outputs: Option<Vec<String>> = None;
+    for &decoder in decoders {
+        let start = Instant::now();
+        let table = if decoder == Decoder::Lookup { Some(build_lookup_table(glyphs)) } else { None };
+        let table_time = start.elapsed();
+        let start = Instant::now();
+        let mut outputs: Vec<String> = Vec::new();
+        for _ in 0..passes {
+            outputs = entries.iter().map(|e| decoder.decode(table.as_ref(), glyphs, e)).collect();
+        }
+        let decode_time = start.elapsed();
+        match &first_outputs {
+            Some(first) => assert!(*first == outputs, "Decoders disagree"),
+            None => first_outputs = Some(outputs),
+        }
 
-    let mut value = 0u128;
-    let mut offset: usize = 0;
-    for &byte in normalized_bytes.iter().take(10) {
-        value = value | ((byte as u128) << offset);
-        offset += 8;
+        if decoder == Decoder::Lookup {
+            println!("  {}: {:?} to build the table + {:?} to decode = {:?}",
+                decoder.name(), table_time, decode_time, table_time + decode_time);
+            lookup_time = Some(table_time + decode_time);
+        } else if let Some(lookup_time) = lookup_time {
+            println!("  {}: {:?} (speedup over lookup: {:.1}x)", decoder.name(), decode_time,
+                lookup_time.as_secs_f64() / decode_time.as_secs_f64());
+        } else {
+            println!("  {}: {:?}", decoder.name(), decode_time);
+        }
     }
-
-    return value;
 }
 
+// Options:
+//   --glyphs FILE  Read the symbols the display shows from FILE (see GlyphSet::load),
+//                  instead of the 7-segment digits
+//   --decoder D    How to find each entry's arrangement: lookup, deduce or search (see
+//                  Decoder), several of them separated by commas (checking that they
+//                  agree), or both for lookup,deduce. Default: lookup for the digits,
+//                  search for other glyph sets.
+//   --bench N      Instead of solving, time the decoders (default: all that support the
+//                  glyph set) decoding every entry N times
 fn main() {
+    let mut glyphs = GlyphSet::digits();
+    let mut decoders: Option<Vec<Decoder>> = None;
+    let mut bench_passes: Option<usize> = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--glyphs" => glyphs = GlyphSet::load(&args.next().expect("--glyphs requires a file")),
+            "--decoder" => {
+                let value = args.next().expect("--decoder requires lookup, deduce, search or both");
+                let mut list: Vec<Decoder> = Vec::new();
+                for name in value.split(',') {
+                    match name {
+                        "lookup" => list.push(Decoder::Lookup),
+                        "deduce" => list.push(Decoder::Deduce),
+                        "search" => list.push(Decoder::Search),
+                        "both" => list.extend([Decoder::Lookup, Decoder::Deduce]),
+                        _ => panic!("Unknown decoder: {}", name),
+                    }
+                }
+                decoders = Some(list);
+            }
+            "--bench" => {
+                let value = args.next().expect("--bench requires a number of passes");
+                bench_passes = Some(value.parse().unwrap_or_else(|_| panic!("Invalid number of passes: {}", value)));
+            }
+            _ => panic!("Unknown argument: {}", arg),
+        }
+    }
+    let is_digits = glyphs == GlyphSet::digits();
+
     // Read all entries from input
     let mut entries: Vec<Entry> = Vec::new();
     while let Some(entry) = read_entry() {
         entries.push(entry);
     }
 
+    if let Some(passes) = bench_passes {
+        let decoders = decoders.unwrap_or_else(|| {
+            let mut all = vec![Decoder::Search];
+            if is_digits {
+                all.insert(0, Decoder::Deduce);
+            }
+            if glyphs.segments.len() <= MAX_LOOKUP_SEGMENTS {
+                all.insert(0, Decoder::Lookup);
+            }
+            all
+        });
+        decoders.iter().for_each(|d| d.check(&glyphs));
+        bench(&entries, &glyphs, &decoders, passes);
+        return;
+    }
+    let decoders = decoders.unwrap_or_else(|| vec![if is_digits { Decoder::Lookup } else { Decoder::Search }]);
+    decoders.iter().for_each(|d| d.check(&glyphs));
+
     // Part 1 - Answer: 349
+    let obvious_sizes = glyphs.obvious_sizes();
     let mut obvious_output_digits_count = 0;
     for entry in entries.iter() {
         obvious_output_digits_count += entry.outputs.iter()
-            .map(|s| { s.len() })
-            .filter(|&n| { n == 2usize || n == 3usize || n == 4usize || n == 7usize })
+            .filter(|s| { obvious_sizes.contains(&(s.len() as u32)) })
             .count();
     }
     println!("Total 'obvious' outputs: {}", obvious_output_digits_count);
 
     // Part 2 - Answer: 1070957
-    // For every possible arrangement, calculate the resulting signal
-    // patterns. Store them in a map to quickly lookup the arrangement
-    // given the unique normalized value.
-    println!("Computing lookup table");
-    let mut arrangements: BTreeMap<u128, Arrangement> = BTreeMap::new();
-    let wire_targets: Vec<char> = vec!['a', 'b', 'c', 'd', 'e', 'f', 'g'];
-    for perm in wire_targets.into_iter().permutations(7) {
-        let arrangement = Arrangement::new(perm);
-        arrangements.insert(arrangement.normalized_id, arrangement);
-    }
-    println!("Computing lookup table - done");
+    let table = if decoders.contains(&Decoder::Lookup) {
+        println!("Computing lookup table");
+        let table = build_lookup_table(&glyphs);
+        println!("Computing lookup table - done");
+        Some(table)
+    } else {
+        None
+    };
 
-    // For every entry in the input, compute it's normalized value and find
-    // it in the pre-computed arrangements.
+    // For every entry in the input, find its arrangement and decode the
+    // outputs. Outputs made only of digits are summed up; others are printed.
+    let numeric = glyphs.symbols.iter().all(|s| s.len() == 1 && s.chars().all(|c| c.is_ascii_digit()));
     let mut decoded_sum = 0;
     for entry in entries.iter() {
-        let entry_id = normalized_value(&entry.signal_patterns);
-        if let Some(arr) = arrangements.get(&entry_id) {
-            let decoded_value = arr.decode_many(&entry.outputs);
-            decoded_sum += decoded_value;
-        }
-        else {
-            panic!("Did not find any arrangement for entry {:?} ({})", entry.signal_patterns, entry_id);
+        let outputs: Vec<String> = decoders.iter()
+            .map(|decoder| decoder.decode(table.as_ref(), &glyphs, entry))
+            .collect();
+        assert!(outputs.iter().all_equal(),
+            "Decoders {:?} disagree for entry {:?}: {:?}", decoders, entry.signal_patterns, outputs);
+        if numeric {
+            decoded_sum += outputs[0].parse::<i64>().unwrap();
+        } else {
+            println!("Decoded: {}", outputs[0]);
         }
     }
-    println!("Decoded sum: {}", decoded_sum);
-}
\ No newline at end of file
+    if numeric {
+        println!("Decoded sum: {}", decoded_sum);
+    }
+}
@@ -0,0 +1,78 @@
+use crate::glyphs::{GlyphSet, PatternId};
+use crate::{normalized_value, Arrangement, Entry};
+
+// Works out an entry's arrangement for any glyph set, by trying to connect
+// each segment to a wire in turn and backing out of dead ends. Returns None
+// if the patterns don't fit any arrangement.
+//
+// A wire can only drive a segment that looks the same from the outside: it
+// must be lit in as many patterns of each size, and lit together with each
+// wire chosen so far as often as the segments are. Usually that leaves one
+// choice at each step.
+pub fn search(entry: &Entry, glyphs: &GlyphSet) -> Option<Arrangement> {
+    let patterns: Vec<PatternId> = entry.signal_patterns.iter().map(|s| glyphs.pattern_id(s)).collect();
+    if patterns.len() != glyphs.patterns.len() {
+        return None;
+    }
+    let mut search = Search {
+        glyphs,
+        patterns: &patterns,
+        entry_id: normalized_value(&entry.signal_patterns, glyphs),
+        wires: Vec::new(),
+    };
+    search.assign(0)
+}
+
+struct Search<'a> {
+    glyphs: &'a GlyphSet,
+    patterns: &'a [PatternId],
+    entry_id: Vec<PatternId>,
+    wires: Vec<usize>, // wires[s] is the wire connected to segment s, so far
+}
+
+// Sizes of the patterns lighting the given bit, in order.
+fn signature(patterns: &[PatternId], bit: usize) -> Vec<u32> {
+    let mut sizes: Vec<u32> = patterns.iter()
+        .filter(|&&p| p & (1 << bit) != 0)
+        .map(|p| p.count_ones())
+        .collect();
+    sizes.sort_unstable();
+    sizes
+}
+
+// Number of patterns lighting both bits.
+fn together(patterns: &[PatternId], bit1: usize, bit2: usize) -> usize {
+    let both: PatternId = (1 << bit1) | (1 << bit2);
+    patterns.iter().filter(|&&p| p & both == both).count()
+}
+
+impl Search<'_> {
+    // Connects segment s and the ones after it, given the wires chosen for
+    // the ones before it.
+    fn assign(&mut self, s: usize) -> Option<Arrangement> {
+        let n = self.glyphs.segments.len();
+        if s == n {
+            let wire_targets: Vec<char> = self.wires.iter().map(|&w| self.glyphs.segments[w]).collect();
+            let arrangement = Arrangement::new(wire_targets, self.glyphs);
+            return if arrangement.normalized_id == self.entry_id { Some(arrangement) } else { None };
+        }
+        let wanted = signature(&self.glyphs.patterns, s);
+        for w in 0..n {
+            if self.wires.contains(&w) || signature(self.patterns, w) != wanted {
+                continue;
+            }
+            let fits = self.wires.iter().enumerate().all(|(prev_s, &prev_w)| {
+                together(&self.glyphs.patterns, s, prev_s) == together(self.patterns, w, prev_w)
+            });
+            if !fits {
+                continue;
+            }
+            self.wires.push(w);
+            if let Some(arrangement) = self.assign(s + 1) {
+                return Some(arrangement);
+            }
+            self.wires.pop();
+        }
+        None
+    }
+}
@@ -1,5 +1,9 @@
 //! Functions commonly used in AOC solutions.
 
+mod bigint;
+
+pub use bigint::BigUint;
+
 /// Reads an optional line from standard input.
 /// 
 /// The resulting line, if any, is not trimmed. On EOF, this